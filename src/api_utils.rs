@@ -5,10 +5,10 @@ pub fn json_list(list: Vec<String>) -> String {
     for (i, item) in list.iter().enumerate() {
         json.push_str(&format!("\"{}\"", item));
         if i < list.len() - 1 {
-            json.push_str(",");
+            json.push(',');
         }
     }
-    json.push_str("]");
+    json.push(']');
     json
 }
 
@@ -0,0 +1,186 @@
+use std::time::Duration;
+
+use crate::engine::{
+    board::{create_board, START_FEN},
+    game::Game,
+    pgn::read_pgn,
+    search::SearchLimits,
+    tt::TranspositionTable,
+    Move,
+};
+
+const USAGE: &str = "usage:
+    chess_engine                         start the server
+    chess_engine perft <depth> [fen]     count leaf nodes of the move tree
+    chess_engine divide <depth> [fen]    perft split by root move
+    chess_engine go [<limit> <value>]... [fen] [moves <move>...]
+                                         search for the best move after the moves, in
+                                         UCI notation. limits are depth, nodes, movestogo,
+                                         and movetime, wtime, btime, winc and binc in
+                                         milliseconds
+    chess_engine pgn <file>              replay the games in a PGN file and print them back
+    chess_engine status [fen] [moves <move>...]
+                                         report checkmate, stalemate or a draw";
+
+// a game from `[fen] [moves <move>...]`, the FEN defaults to the start position
+fn game_from_args(args: &[String]) -> Result<Game, String> {
+    let split = args.iter().position(|a| a == "moves").unwrap_or(args.len());
+    let fen = if split > 0 {
+        args[..split].join(" ")
+    } else {
+        START_FEN.to_string()
+    };
+    let mut moves = Vec::new();
+    for m in args[split..].iter().skip(1) {
+        moves.push(m.parse::<Move>().map_err(|e| format!("{}: {}", m, e))?);
+    }
+    Game::replay(&fen, &moves).map_err(|e| e.to_string())
+}
+
+// runs a command line tool and returns the process exit code
+pub fn run(args: &[String]) -> i32 {
+    match args[0].as_str() {
+        "perft" | "divide" => {
+            let depth = match args.get(1).map(|d| d.parse::<u8>()) {
+                Some(Ok(depth)) => depth,
+                _ => {
+                    eprintln!("{}", USAGE);
+                    return 2;
+                }
+            };
+            let fen = if args.len() > 2 {
+                args[2..].join(" ")
+            } else {
+                START_FEN.to_string()
+            };
+            let mut board = match create_board(&fen) {
+                Ok(board) => board,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    return 1;
+                }
+            };
+            let now = std::time::Instant::now();
+            let nodes = if args[0] == "divide" {
+                let split = board.divide(depth);
+                for (m, nodes) in split.iter() {
+                    println!("{}: {}", m, nodes);
+                }
+                println!();
+                split.iter().map(|(_, nodes)| nodes).sum()
+            } else {
+                board.perft(depth)
+            };
+            let secs = now.elapsed().as_secs_f64();
+            println!("Nodes searched: {}", nodes);
+            println!("time taken: {:.3}s ({:.0} nps)", secs, nodes as f64 / secs);
+            0
+        }
+        "go" => {
+            let mut limits = SearchLimits::default();
+            let mut i = 1;
+            while i + 1 < args.len() {
+                let value = match args[i + 1].parse::<u64>() {
+                    Ok(value) => value,
+                    Err(_) => break,
+                };
+                let ms = Some(Duration::from_millis(value));
+                match args[i].as_str() {
+                    "depth" => limits.depth = Some(value.min(u8::MAX as u64) as u8),
+                    "nodes" => limits.nodes = Some(value),
+                    "movestogo" => limits.movestogo = Some(value.min(u32::MAX as u64) as u32),
+                    "movetime" => limits.movetime = ms,
+                    "wtime" => limits.wtime = ms,
+                    "btime" => limits.btime = ms,
+                    "winc" => limits.winc = ms,
+                    "binc" => limits.binc = ms,
+                    _ => break,
+                }
+                i += 2;
+            }
+            // same depth the engine has always used
+            if limits.depth.is_none()
+                && limits.nodes.is_none()
+                && limits.movetime.is_none()
+                && limits.wtime.is_none()
+                && limits.btime.is_none()
+            {
+                limits.depth = Some(3);
+            }
+            let mut game = match game_from_args(&args[i..]) {
+                Ok(game) => game,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    return 1;
+                }
+            };
+            let status = game.status();
+            if status.is_game_over() {
+                println!("{}", status);
+                return 0;
+            }
+            let result = game.search(&limits, &mut TranspositionTable::default());
+            println!("{}", result);
+            match result.best_move {
+                Some(m) => println!("bestmove {}", m),
+                None => println!("no legal moves"),
+            }
+            0
+        }
+        "pgn" => {
+            let path = match args.get(1) {
+                Some(path) => path,
+                None => {
+                    eprintln!("{}", USAGE);
+                    return 2;
+                }
+            };
+            let text = match std::fs::read_to_string(path) {
+                Ok(text) => text,
+                Err(e) => {
+                    eprintln!("{}: {}", path, e);
+                    return 1;
+                }
+            };
+            match read_pgn(&text) {
+                Ok(games) => {
+                    for game in games {
+                        println!("{}", game.to_pgn());
+                    }
+                    0
+                }
+                Err(e) => {
+                    eprintln!("{}: {}", path, e);
+                    1
+                }
+            }
+        }
+        "status" => {
+            // repetitions are only seen among the moves given, a FEN
+            // carries no history
+            match game_from_args(&args[1..]) {
+                Ok(mut game) => {
+                    let status = game.status();
+                    if status.is_claimable() {
+                        println!("{} can be claimed", status);
+                    } else {
+                        println!("{}", status);
+                    }
+                    let repetitions = game.repetition_count();
+                    if repetitions > 1 {
+                        println!("the position has occurred {} times", repetitions);
+                    }
+                    0
+                }
+                Err(e) => {
+                    eprintln!("{}", e);
+                    1
+                }
+            }
+        }
+        _ => {
+            eprintln!("{}", USAGE);
+            2
+        }
+    }
+}
@@ -0,0 +1,295 @@
+use std::sync::OnceLock;
+
+use super::{
+    bitboard::{
+        ray_attacks, step_attacks, Bitboard, DIAGONAL_DIRECTIONS, EMPTY, KING_OFFSETS,
+        KNIGHT_OFFSETS, ORTHOGONAL_DIRECTIONS,
+    },
+    piece::PieceColor,
+    square::Square,
+};
+
+/*
+Precomputed attack tables, built once on first use. Knights, kings and pawns
+get one bitboard per square. Bishops and rooks use magic bitboards: the
+blockers on the relevant squares are multiplied by a per-square magic
+number, and the top bits of the product index that square's attack sets.
+ */
+
+// found once by trying sparse random numbers until one maps every blocker
+// configuration of the square without a harmful collision
+const BISHOP_MAGICS: [u64; 64] = [
+    0x48081010008a2a80,
+    0x0b68493102020010,
+    0x0041020200410003,
+    0x3088394100080004,
+    0x0004042080100109,
+    0x0500822021000000,
+    0x0001080104200221,
+    0x0044140202100440,
+    0x0042715111080080,
+    0x0808101042148020,
+    0x0001c10206025232,
+    0x0000308902008080,
+    0x1a02011040100080,
+    0x0020011402405014,
+    0xc0030c00a4100800,
+    0x009008420084a042,
+    0x4020000420029a00,
+    0x0020830858010458,
+    0x9006040404040709,
+    0x0402022020204000,
+    0x0419010820080818,
+    0x024842020110a000,
+    0x0000800118901084,
+    0x0005100204520205,
+    0x2802204008081000,
+    0x000a090090210823,
+    0x0011220114080200,
+    0x3040080800820040,
+    0x0001001001004000,
+    0x32022a0009880101,
+    0x1141222604020102,
+    0x0040410012008220,
+    0x48c8080410410410,
+    0x0212484411421000,
+    0x004a00820010002a,
+    0x2200480800920a00,
+    0x0210020081001004,
+    0x4881080200132200,
+    0x004101020a810800,
+    0x10608c2044008200,
+    0x0490900808002004,
+    0x40108a88a0088808,
+    0x8401882808000400,
+    0x1002204208000080,
+    0x0000202414000440,
+    0x00602c0102488200,
+    0x0304c82204000048,
+    0x0004009082006100,
+    0x0803081110088421,
+    0x04008084100200f0,
+    0x0002004108210024,
+    0x000001a042020808,
+    0x2000020410440004,
+    0x0900042004610440,
+    0x0110250810840004,
+    0x0420840404842200,
+    0x1082010841304800,
+    0xc080820084040204,
+    0x1100008121081850,
+    0x0064020800840421,
+    0x4010210044a08208,
+    0x00101022280a0820,
+    0x4000054810140080,
+    0x0088223808010014,
+];
+const ROOK_MAGICS: [u64; 64] = [
+    0x0480046281400010,
+    0x1040100040002002,
+    0x8780200008300180,
+    0x8880060800100080,
+    0x8200020104100820,
+    0x0200100104020008,
+    0x0480010000800200,
+    0x4e00008201005024,
+    0x3001002040800100,
+    0x4200402010004000,
+    0x0118802000801000,
+    0x0020808010000800,
+    0x0000800400080080,
+    0x0002000802000410,
+    0x1004800a00800500,
+    0x0000802553000080,
+    0x6040288000804011,
+    0x1110084000200840,
+    0x8082060024104080,
+    0x0010010008201100,
+    0x0000808008000402,
+    0x0000808004000200,
+    0x0015440002108841,
+    0x080e020034004081,
+    0x0800802080004000,
+    0x2200500440002002,
+    0x1000104100200104,
+    0x0802001200082040,
+    0x0001000500100800,
+    0x0000020080800400,
+    0x0000c10400021008,
+    0x01c25d0e00004084,
+    0x8800400080800020,
+    0x0280804000802003,
+    0x0102110043002000,
+    0x1000200a02001041,
+    0x0040080101000410,
+    0x0048040080800200,
+    0x0000620104001008,
+    0x0800040042002091,
+    0x01a0400020828000,
+    0x3090006000c54000,
+    0x5080402001070010,
+    0x00a21200400a0020,
+    0x0a08000400088080,
+    0x8001004400090002,
+    0xc000100801440002,
+    0x080002c884020031,
+    0x4c20304100800b00,
+    0x2140984000200080,
+    0x0206002850438200,
+    0x0800210010000900,
+    0x0901000410080100,
+    0x0002008024000280,
+    0x0109000200040100,
+    0x00002080410c0600,
+    0x8100418000506103,
+    0x0000108040220102,
+    0x0a40e028820250c2,
+    0x0482210500100009,
+    0x0801000800021085,
+    0x0816006810010422,
+    0x020a000450a80102,
+    0x00000081a044030e,
+];
+
+#[derive(Clone, Copy, Default)]
+struct Magic {
+    mask: Bitboard,
+    magic: u64,
+    shift: u32,
+    offset: usize,
+}
+
+impl Magic {
+    fn index(&self, occupied: Bitboard) -> usize {
+        self.offset + ((occupied & self.mask).wrapping_mul(self.magic) >> self.shift) as usize
+    }
+}
+
+struct AttackTables {
+    knight: [Bitboard; 64],
+    king: [Bitboard; 64],
+    // indexed by `PieceColor as usize`
+    pawn: [[Bitboard; 64]; 2],
+    bishop_magics: [Magic; 64],
+    rook_magics: [Magic; 64],
+    sliding: Vec<Bitboard>,
+}
+
+static TABLES: OnceLock<AttackTables> = OnceLock::new();
+
+fn tables() -> &'static AttackTables {
+    TABLES.get_or_init(build_tables)
+}
+
+// builds the tables now instead of on the first lookup
+pub fn init() {
+    tables();
+}
+
+// squares whose occupancy can change the attack set: the rays from `sq`,
+// minus the last square of each ray since a piece there blocks nothing
+fn relevant_mask(sq: Square, directions: &[(i8, i8)]) -> Bitboard {
+    let mut mask = EMPTY;
+    for (df, dr) in directions {
+        let mut current = sq;
+        while let Some(target) = current.offset(*df, *dr) {
+            if target.offset(*df, *dr).is_none() {
+                break;
+            }
+            mask |= target.bit();
+            current = target;
+        }
+    }
+    mask
+}
+
+// builds the attack table for `sq` from its magic and appends it to `sliding`
+fn build_magic(
+    sq: Square,
+    directions: &[(i8, i8)],
+    magic: u64,
+    sliding: &mut Vec<Bitboard>,
+) -> Magic {
+    let mask = relevant_mask(sq, directions);
+    let bits = mask.count_ones();
+    let entry = Magic {
+        mask,
+        magic,
+        shift: 64 - bits,
+        offset: sliding.len(),
+    };
+    sliding.resize(entry.offset + (1 << bits), EMPTY);
+    // enumerate every subset of the mask (carry-rippler trick)
+    let mut subset = EMPTY;
+    loop {
+        let attacks = ray_attacks(sq, subset, directions);
+        let i = entry.index(subset);
+        if sliding[i] != EMPTY && sliding[i] != attacks {
+            panic!("bad magic number for square {}", sq);
+        }
+        sliding[i] = attacks;
+        subset = subset.wrapping_sub(mask) & mask;
+        if subset == EMPTY {
+            break;
+        }
+    }
+    entry
+}
+
+fn build_tables() -> AttackTables {
+    let mut tables = AttackTables {
+        knight: [EMPTY; 64],
+        king: [EMPTY; 64],
+        pawn: [[EMPTY; 64]; 2],
+        bishop_magics: [Magic::default(); 64],
+        rook_magics: [Magic::default(); 64],
+        sliding: Vec::new(),
+    };
+    for sq in Square::all() {
+        let i = sq.index();
+        tables.knight[i] = step_attacks(sq, &KNIGHT_OFFSETS);
+        tables.king[i] = step_attacks(sq, &KING_OFFSETS);
+        tables.pawn[PieceColor::WHITE as usize][i] = step_attacks(sq, &[(-1, 1), (1, 1)]);
+        tables.pawn[PieceColor::BLACK as usize][i] = step_attacks(sq, &[(-1, -1), (1, -1)]);
+        tables.bishop_magics[i] = build_magic(
+            sq,
+            &DIAGONAL_DIRECTIONS,
+            BISHOP_MAGICS[i],
+            &mut tables.sliding,
+        );
+        tables.rook_magics[i] = build_magic(
+            sq,
+            &ORTHOGONAL_DIRECTIONS,
+            ROOK_MAGICS[i],
+            &mut tables.sliding,
+        );
+    }
+    tables
+}
+
+pub fn knight_attacks(sq: Square) -> Bitboard {
+    tables().knight[sq.index()]
+}
+
+pub fn king_attacks(sq: Square) -> Bitboard {
+    tables().king[sq.index()]
+}
+
+// squares attacked by a pawn of `color` standing on `sq`
+pub fn pawn_attacks(sq: Square, color: PieceColor) -> Bitboard {
+    tables().pawn[color as usize][sq.index()]
+}
+
+pub fn bishop_attacks(sq: Square, occupied: Bitboard) -> Bitboard {
+    let t = tables();
+    t.sliding[t.bishop_magics[sq.index()].index(occupied)]
+}
+
+pub fn rook_attacks(sq: Square, occupied: Bitboard) -> Bitboard {
+    let t = tables();
+    t.sliding[t.rook_magics[sq.index()].index(occupied)]
+}
+
+pub fn queen_attacks(sq: Square, occupied: Bitboard) -> Bitboard {
+    bishop_attacks(sq, occupied) | rook_attacks(sq, occupied)
+}
@@ -0,0 +1,83 @@
+use super::square::Square;
+
+// bit n is set when the square with index n (so bit 0 is a8) is occupied
+pub type Bitboard = u64;
+
+pub const EMPTY: Bitboard = 0;
+pub const RANK_8: Bitboard = 0xff;
+pub const RANK_1: Bitboard = RANK_8 << 56;
+
+// offsets are (files, ranks), see Square::offset
+pub const KNIGHT_OFFSETS: [(i8, i8); 8] = [
+    (1, 2),
+    (2, 1),
+    (2, -1),
+    (1, -2),
+    (-1, -2),
+    (-2, -1),
+    (-2, 1),
+    (-1, 2),
+];
+pub const KING_OFFSETS: [(i8, i8); 8] = [
+    (1, 0),
+    (0, 1),
+    (-1, 0),
+    (0, -1),
+    (1, 1),
+    (-1, -1),
+    (-1, 1),
+    (1, -1),
+];
+pub const DIAGONAL_DIRECTIONS: [(i8, i8); 4] = [(1, 1), (1, -1), (-1, 1), (-1, -1)];
+pub const ORTHOGONAL_DIRECTIONS: [(i8, i8); 4] = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+
+pub fn is_set(bb: Bitboard, sq: Square) -> bool {
+    bb & sq.bit() != 0
+}
+
+// iterates over the set squares, lowest first
+pub struct BitIter(Bitboard);
+
+impl Iterator for BitIter {
+    type Item = Square;
+    fn next(&mut self) -> Option<Square> {
+        if self.0 == EMPTY {
+            return None;
+        }
+        let sq = Square::from_index(self.0.trailing_zeros() as u8);
+        self.0 &= self.0 - 1;
+        Some(sq)
+    }
+}
+
+pub fn squares(bb: Bitboard) -> BitIter {
+    BitIter(bb)
+}
+
+pub fn step_attacks(sq: Square, offsets: &[(i8, i8)]) -> Bitboard {
+    let mut attacks = EMPTY;
+    for (df, dr) in offsets {
+        if let Some(target) = sq.offset(*df, *dr) {
+            attacks |= target.bit();
+        }
+    }
+    attacks
+}
+
+// walks each ray until it leaves the board or hits a piece; the blocking
+// square is included, whoever owns it. this is slow, and only used to build
+// the lookup tables in attacks.rs
+pub fn ray_attacks(sq: Square, occupied: Bitboard, directions: &[(i8, i8)]) -> Bitboard {
+    let mut attacks = EMPTY;
+    for (df, dr) in directions {
+        let mut current = sq;
+        while let Some(target) = current.offset(*df, *dr) {
+            attacks |= target.bit();
+            if is_set(occupied, target) {
+                break;
+            }
+            current = target;
+        }
+    }
+    attacks
+}
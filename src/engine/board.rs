@@ -1,235 +1,439 @@
-use std::{
-    collections::HashMap,
-    fmt::{self},
-};
-
-use crate::engine::move_as_string;
+use std::fmt::{self};
 
 use super::{
-    decode_move,
-    moves::{all_possible_raw_moves, all_possible_valid_moves, find_in_raw_move_targets},
-    piece::{Piece, PieceColor, PieceType},
+    attacks::pawn_attacks,
+    bitboard::{squares, Bitboard, EMPTY, RANK_1, RANK_8},
+    moves::{all_possible_valid_moves, find_in_raw_move_targets},
+    piece::{Piece, PieceColor, PieceType, PIECE_TYPES},
+    search::{Score, MAX_EVAL},
+    square::Square,
     weights::{get_piece_weight, get_positional_weight},
-    Move,
+    zobrist::{castling_key, en_passant_key, piece_key, side_key},
+    Move, DOUBLE_PAWN_PUSH, EN_PASSANT_CAPTURE, KING_CASTLE, QUEEN_CASTLE,
 };
-pub type Position = u8;
 
-pub fn decode_pos(position: &Position) -> (i8, i8) {
-    let rank = position / 8;
-    let file = position % 8;
-    (rank as i8, file as i8)
-}
-pub fn encode_pos(rank: u8, file: u8) -> Position {
-    (rank * 8 + file) as Position
-}
+pub const WHITE_KINGSIDE: u8 = 0b0001;
+pub const WHITE_QUEENSIDE: u8 = 0b0010;
+pub const BLACK_KINGSIDE: u8 = 0b0100;
+pub const BLACK_QUEENSIDE: u8 = 0b1000;
 
-pub fn pos_as_string(pos: &Position) -> String {
-    let (r, f) = decode_pos(&pos);
-    format!(
-        "{}{}",
-        (f + 'a' as i8) as u8 as char,
-        (7 - r + '1' as i8) as u8 as char
-    )
-}
+// (right, color, king square, rook square, FEN letter)
+const CASTLING_SQUARES: [(u8, PieceColor, Square, Square, &str); 4] = [
+    (
+        WHITE_KINGSIDE,
+        PieceColor::WHITE,
+        Square::E1,
+        Square::H1,
+        "K",
+    ),
+    (
+        WHITE_QUEENSIDE,
+        PieceColor::WHITE,
+        Square::E1,
+        Square::A1,
+        "Q",
+    ),
+    (
+        BLACK_KINGSIDE,
+        PieceColor::BLACK,
+        Square::E8,
+        Square::H8,
+        "k",
+    ),
+    (
+        BLACK_QUEENSIDE,
+        PieceColor::BLACK,
+        Square::E8,
+        Square::A8,
+        "q",
+    ),
+];
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Board {
-    pub squares: [Option<Piece>; 64],
-    pub piecemap: HashMap<u8, Piece>,
+    // squares holding each piece type, indexed by `PieceType as usize`
+    pub pieces: [Bitboard; 6],
+    // squares holding pieces of each color, indexed by `PieceColor as usize`
+    pub colors: [Bitboard; 2],
     pub side_to_move: PieceColor,
     pub castling_rights: u8,
-    pub en_passant_square: Option<Position>,
-    pub halfmove_clock: u8,  //ignoring this for now
-    pub fullmove_number: u8, //ignoring this for now
+    pub en_passant_square: Option<Square>,
+    // plies since the last capture or pawn move
+    pub halfmove_clock: u16,
+    // starts at 1 and is incremented after black moves
+    pub fullmove_number: u16,
+    // zobrist hash of the position, kept up to date by set_piece and make_move
+    pub hash: u64,
 }
 
+// everything make_move can't recompute when undoing a move
 pub struct MoveContext {
     chessmove: Move,
     killed_piece: Option<Piece>,
-    //todo: add castling rights and en passant square
+    castling_rights: u8,
+    en_passant_square: Option<Square>,
+    halfmove_clock: u16,
+    fullmove_number: u16,
+    hash: u64,
+    // snapshot used to verify that unmake_move restores the board exactly
+    #[cfg(debug_assertions)]
+    board_before: Box<Board>,
 }
 
-impl Board {
-    pub fn get_piece(&self, square: u8) -> Option<Piece> {
-        self.squares[square as usize]
+// castling rights which survive a piece moving from or to `sq`
+fn castling_rights_mask(sq: Square) -> u8 {
+    match sq {
+        Square::A8 => !BLACK_QUEENSIDE,
+        Square::E8 => !(BLACK_KINGSIDE | BLACK_QUEENSIDE),
+        Square::H8 => !BLACK_KINGSIDE,
+        Square::A1 => !WHITE_QUEENSIDE,
+        Square::E1 => !(WHITE_KINGSIDE | WHITE_QUEENSIDE),
+        Square::H1 => !WHITE_KINGSIDE,
+        _ => 0b1111,
     }
-    pub fn set_piece(&mut self, square: u8, piece: Option<Piece>) {
-        if piece.is_none() {
-            self.piecemap.remove(&square);
+}
+
+// square of the pawn removed by an en passant capture
+fn en_passant_capture_square(from: Square, to: Square) -> Square {
+    Square::from_coords(to.file(), from.rank()).unwrap()
+}
+
+// rook (from, to) squares for a move, if that move is a castle
+fn castling_rook_squares(m: &Move) -> Option<(Square, Square)> {
+    let from = m.from();
+    let along = |files: i8| from.offset(files, 0).unwrap();
+    match m.flags() {
+        KING_CASTLE => Some((along(3), along(1))),
+        QUEEN_CASTLE => Some((along(-4), along(-1))),
+        _ => None,
+    }
+}
+
+impl Board {
+    pub fn get_piece(&self, square: Square) -> Option<Piece> {
+        let b = square.bit();
+        let color = if self.colors[PieceColor::WHITE as usize] & b != 0 {
+            PieceColor::WHITE
+        } else if self.colors[PieceColor::BLACK as usize] & b != 0 {
+            PieceColor::BLACK
         } else {
-            self.piecemap.insert(square, piece.unwrap());
+            return None;
+        };
+        let piece_type = PIECE_TYPES
+            .into_iter()
+            .find(|t| self.pieces[*t as usize] & b != 0)?;
+        Some(Piece { color, piece_type })
+    }
+    pub fn set_piece(&mut self, square: Square, piece: Option<Piece>) {
+        if let Some(old) = self.get_piece(square) {
+            self.hash ^= piece_key(&old, square);
         }
-        self.squares[square as usize] = piece;
+        if let Some(new) = piece {
+            self.hash ^= piece_key(&new, square);
+        }
+        let b = square.bit();
+        for bb in self.pieces.iter_mut().chain(self.colors.iter_mut()) {
+            *bb &= !b;
+        }
+        if let Some(p) = piece {
+            self.pieces[p.piece_type as usize] |= b;
+            self.colors[p.color as usize] |= b;
+        }
+    }
+    pub fn occupied(&self) -> Bitboard {
+        self.colors[PieceColor::WHITE as usize] | self.colors[PieceColor::BLACK as usize]
+    }
+    pub fn color_bb(&self, color: PieceColor) -> Bitboard {
+        self.colors[color as usize]
+    }
+    pub fn piece_bb(&self, color: PieceColor, piece_type: PieceType) -> Bitboard {
+        self.colors[color as usize] & self.pieces[piece_type as usize]
+    }
+    /*
+    the en passant square as far as the hash is concerned: only when a pawn
+    of the side to move stands next to the pushed pawn, since otherwise
+    the position is the same as without it and repetitions must match
+     */
+    fn hashed_en_passant_square(&self) -> Option<Square> {
+        let side = self.side_to_move;
+        self.en_passant_square.filter(|ep| {
+            pawn_attacks(*ep, side.opponent_color()) & self.piece_bb(side, PieceType::PAWN) != EMPTY
+        })
+    }
+    // hashes the position from scratch; make_move keeps `hash` equal to this
+    pub fn compute_hash(&self) -> u64 {
+        let mut hash =
+            castling_key(self.castling_rights) ^ en_passant_key(self.hashed_en_passant_square());
+        if self.side_to_move == PieceColor::BLACK {
+            hash ^= side_key();
+        }
+        for pos in squares(self.occupied()) {
+            hash ^= piece_key(&self.get_piece(pos).unwrap(), pos);
+        }
+        hash
+    }
+    pub fn king_square(&self, color: PieceColor) -> Option<Square> {
+        squares(self.piece_bb(color, PieceType::KING)).next()
+    }
+    fn has_castling_pieces(&self, color: PieceColor, king: Square, rook: Square) -> bool {
+        self.get_piece(king) == Some(Piece::new(color, PieceType::KING))
+            && self.get_piece(rook) == Some(Piece::new(color, PieceType::ROOK))
     }
+    // every right whose king and rook are still on their starting squares
+    fn castling_rights_from_placement(&self) -> u8 {
+        CASTLING_SQUARES
+            .iter()
+            .filter(|(_, color, king, rook, _)| self.has_castling_pieces(*color, *king, *rook))
+            .fold(0, |rights, (right, ..)| rights | right)
+    }
+    // checks that the position could arise in a game: one king each, kings
+    // apart, no pawns on the back ranks, reachable material, the side that
+    // just moved not in check, and castling and en passant state that agree
+    // with the pieces on the board
     pub fn validate(&self) -> Result<(), String> {
-        //a valid board has both kings and not adjacent
-        let mut white_king = false;
-        let mut black_king = false;
-        let mut white_king_pos = 0;
-        let mut black_king_pos = 0;
-        for i in 0..64 {
-            let piece = self.get_piece(i);
-            if piece.is_some() {
-                if piece.unwrap().piece_type == PieceType::KING {
-                    if piece.unwrap().color == PieceColor::WHITE {
-                        white_king = true;
-                        white_king_pos = i;
-                    } else {
-                        black_king = true;
-                        black_king_pos = i;
-                    }
-                }
+        for color in [PieceColor::WHITE, PieceColor::BLACK] {
+            let name = match color {
+                PieceColor::WHITE => "white",
+                PieceColor::BLACK => "black",
+            };
+            let count = |t: PieceType| self.piece_bb(color, t).count_ones();
+            match count(PieceType::KING) {
+                0 => return Err(format!("missing {} king", name)),
+                1 => {}
+                n => return Err(format!("{} has {} kings", name, n)),
             }
+            if self.color_bb(color).count_ones() > 16 {
+                return Err(format!("{} has more than 16 pieces", name));
+            }
+            let pawns = count(PieceType::PAWN);
+            if pawns > 8 {
+                return Err(format!("{} has {} pawns", name, pawns));
+            }
+            // every piece beyond the starting set must have been a pawn
+            let promoted = count(PieceType::QUEEN).saturating_sub(1)
+                + count(PieceType::ROOK).saturating_sub(2)
+                + count(PieceType::BISHOP).saturating_sub(2)
+                + count(PieceType::KNIGHT).saturating_sub(2);
+            if pawns + promoted > 8 {
+                return Err(format!(
+                    "{} has {} pawns and at least {} promoted pieces",
+                    name, pawns, promoted
+                ));
+            }
+        }
+        if self.pieces[PieceType::PAWN as usize] & (RANK_1 | RANK_8) != EMPTY {
+            return Err("pawn on the first or eighth rank".into());
         }
-        if !white_king || !black_king {
-            return Err("Invalid FEN string: missing king".into());
+        let white_king_pos = self.king_square(PieceColor::WHITE).unwrap();
+        let black_king_pos = self.king_square(PieceColor::BLACK).unwrap();
+        if white_king_pos.file().abs_diff(black_king_pos.file()) <= 1
+            && white_king_pos.rank().abs_diff(black_king_pos.rank()) <= 1
+        {
+            return Err("kings are adjacent".into());
         }
-        let (wr, wf) = decode_pos(&white_king_pos);
-        let (br, bf) = decode_pos(&black_king_pos);
-        let dist = ((wr - br).pow(2) + (wf - bf).pow(2)) as f32;
-        if dist < 1.5 {
-            return Err("Invalid FEN string: kings are adjacent".into());
+        if self.has_check(&self.side_to_move.opponent_color()) {
+            return Err("the side not to move is in check".into());
+        }
+        for (right, color, king, rook, name) in CASTLING_SQUARES {
+            if self.castling_rights & right != 0 && !self.has_castling_pieces(color, king, rook) {
+                return Err(format!(
+                    "castling right {} without king and rook on their starting squares",
+                    name
+                ));
+            }
+        }
+        if let Some(ep) = self.en_passant_square {
+            // the pawn that just moved two squares stands in front of the en
+            // passant square, and the squares it passed over are empty
+            let mover = self.side_to_move.opponent_color();
+            let (rank, forward) = match mover {
+                PieceColor::WHITE => (2, 1),
+                PieceColor::BLACK => (5, -1),
+            };
+            let origin = ep.offset(0, -forward);
+            let pawn = ep.offset(0, forward);
+            if ep.rank() != rank
+                || self.get_piece(ep).is_some()
+                || origin.and_then(|sq| self.get_piece(sq)).is_some()
+                || pawn.and_then(|sq| self.get_piece(sq))
+                    != Some(Piece::new(mover, PieceType::PAWN))
+            {
+                return Err(format!(
+                    "en passant square {} does not follow a double pawn push",
+                    ep
+                ));
+            }
         }
         Ok(())
     }
     //panics if move is invalid
     pub fn make_move(&mut self, m: Move) -> MoveContext {
-        let (from, to) = decode_move(&m);
-        let dead = self.get_piece(to);
+        #[cfg(debug_assertions)]
+        let board_before = Box::new(self.clone());
+        let hash = self.hash;
+        let en_passant_hashed = self.hashed_en_passant_square();
+        let (from, to) = (m.from(), m.to());
+        let mut dead = self.get_piece(to);
         let piece = self.get_piece(from);
         if piece.is_none() {
-            panic!("No piece at source square for move: {}", move_as_string(&m));
+            panic!("No piece at source square for move: {}", m);
+        }
+        let flags = m.flags();
+        let en_passant_square = self.en_passant_square;
+        if flags == EN_PASSANT_CAPTURE {
+            let ep = en_passant_capture_square(from, to);
+            dead = self.get_piece(ep);
+            self.set_piece(ep, None);
+        }
+        self.en_passant_square = None;
+        if flags == DOUBLE_PAWN_PUSH {
+            // the square the pawn passed over
+            self.en_passant_square =
+                Square::from_coords(from.file(), (from.rank() + to.rank()) / 2);
+        }
+        if let Some((rook_from, rook_to)) = castling_rook_squares(&m) {
+            self.set_piece(rook_to, self.get_piece(rook_from));
+            self.set_piece(rook_from, None);
+        }
+        match m.promotion_piece() {
+            Some(piece_type) => self.set_piece(
+                to,
+                Some(Piece {
+                    color: piece.unwrap().color,
+                    piece_type,
+                }),
+            ),
+            None => self.set_piece(to, piece),
         }
-        self.set_piece(to, piece);
         self.set_piece(from, None);
+        let castling_rights = self.castling_rights;
+        self.castling_rights &= castling_rights_mask(from) & castling_rights_mask(to);
+        self.hash ^= castling_key(castling_rights) ^ castling_key(self.castling_rights);
+        self.hash ^= side_key();
+        let halfmove_clock = self.halfmove_clock;
+        if dead.is_some() || piece.unwrap().piece_type == PieceType::PAWN {
+            self.halfmove_clock = 0;
+        } else {
+            self.halfmove_clock = self.halfmove_clock.saturating_add(1);
+        }
+        let fullmove_number = self.fullmove_number;
+        if self.side_to_move == PieceColor::BLACK {
+            self.fullmove_number = self.fullmove_number.saturating_add(1);
+        }
         self.side_to_move = self.side_to_move.opponent_color();
+        // only known once the side to move has changed
+        self.hash ^=
+            en_passant_key(en_passant_hashed) ^ en_passant_key(self.hashed_en_passant_square());
+        debug_assert_eq!(
+            self.hash,
+            self.compute_hash(),
+            "incremental hash diverged after {}",
+            m
+        );
         MoveContext {
             chessmove: m,
             killed_piece: dead,
+            castling_rights,
+            en_passant_square,
+            halfmove_clock,
+            fullmove_number,
+            hash,
+            #[cfg(debug_assertions)]
+            board_before,
         }
     }
     pub fn unmake_move(&mut self, m: MoveContext) {
-        let (from, to) = decode_move(&m.chessmove);
+        let (from, to) = (m.chessmove.from(), m.chessmove.to());
         let piece = self.get_piece(to);
         if piece.is_none() {
-            panic!(
-                "No piece at destination square for move: {}",
-                move_as_string(&m.chessmove)
+            panic!("No piece at destination square for move: {}", m.chessmove);
+        }
+        if let Some((rook_from, rook_to)) = castling_rook_squares(&m.chessmove) {
+            self.set_piece(rook_from, self.get_piece(rook_to));
+            self.set_piece(rook_to, None);
+        }
+        if m.chessmove.promotion_piece().is_some() {
+            self.set_piece(
+                from,
+                Some(Piece {
+                    color: piece.unwrap().color,
+                    piece_type: PieceType::PAWN,
+                }),
             );
+        } else {
+            self.set_piece(from, piece);
+        }
+        if m.chessmove.flags() == EN_PASSANT_CAPTURE {
+            self.set_piece(to, None);
+            self.set_piece(en_passant_capture_square(from, to), m.killed_piece);
+        } else {
+            self.set_piece(to, m.killed_piece);
         }
-        self.set_piece(from, piece);
-        self.set_piece(to, m.killed_piece);
+        self.castling_rights = m.castling_rights;
+        self.en_passant_square = m.en_passant_square;
+        self.halfmove_clock = m.halfmove_clock;
+        self.fullmove_number = m.fullmove_number;
+        self.hash = m.hash;
         self.side_to_move = self.side_to_move.opponent_color();
+        #[cfg(debug_assertions)]
+        {
+            debug_assert_eq!(
+                self.to_fen(),
+                m.board_before.to_fen(),
+                "unmake_move did not restore the position after {}",
+                m.chessmove
+            );
+            debug_assert!(
+                *self == *m.board_before,
+                "unmake_move did not restore the board after {}",
+                m.chessmove
+            );
+        }
     }
     // this function checks whether the current side to move has
     // any moves which coincide with the king of the color `col`
     pub fn has_check(&self, col: &PieceColor) -> bool {
         //board MUST have both kings
 
-        let k_option = self.squares.iter().position(|&x| {
-            x.is_some() && x.unwrap().color == *col && x.unwrap().piece_type == PieceType::KING
-        });
+        let k_option = self.king_square(*col);
         if k_option.is_none() {
             panic!("King of color {:?} not found on board", col);
         }
-        let k = k_option.unwrap() as Position;
+        let k = k_option.unwrap();
 
-        // println!("King position: {:?}", decode_pos(&k));
+        // println!("King position: {}", k);
 
-        return find_in_raw_move_targets(self, &k, &col.opponent_color());
+        find_in_raw_move_targets(self, &k, &col.opponent_color())
     }
-    pub fn best_move(&mut self, depth: u8) -> (f32, Option<Move>) {
-        let now = std::time::Instant::now();
-        let mut nodes_scanned = 0;
-        let (eval, mov) = self.minimax(depth, f32::NEG_INFINITY, f32::INFINITY, &mut nodes_scanned);
-        println!("time taken: {:?}", now.elapsed().as_secs_f32());
-        println!("nodes scanned: {}", nodes_scanned);
-        (eval, mov)
-    }
-    fn minimax(
-        &mut self,
-        depth: u8,
-        mut alpha: f32,
-        mut beta: f32,
-        nodes_scanned: &mut i32,
-    ) -> (f32, Option<Move>) {
-        *nodes_scanned += 1;
-        let v_moves = all_possible_valid_moves(self);
-        if depth == 0 || v_moves.len() == 0 {
-            //todo: memoize fen and score
-            return (self.evaluate(), None);
-        }
-        let mut best_move = None;
-        let ret_eval: f32;
-        if self.side_to_move == PieceColor::WHITE {
-            let mut max_eval = f32::NEG_INFINITY;
-            for m in v_moves {
-                let ctx = self.make_move(m);
-                let (eval, _) = self.minimax(depth - 1, alpha, beta, nodes_scanned);
-                if eval > max_eval {
-                    max_eval = eval;
-                    best_move = Some(m);
-                }
-                self.unmake_move(ctx);
-                alpha = alpha.max(eval);
-                if beta <= alpha {
-                    break;
-                }
-            }
-            ret_eval = max_eval;
-        } else {
-            let mut min_eval = f32::INFINITY;
-            for m in v_moves {
-                let ctx = self.make_move(m);
-                let (eval, _) = self.minimax(depth - 1, alpha, beta, nodes_scanned);
-                if eval < min_eval {
-                    min_eval = eval;
-                    best_move = Some(m);
-                }
-                self.unmake_move(ctx);
-                beta = beta.min(eval);
-                if beta <= alpha {
-                    break;
-                }
-            }
-            ret_eval = min_eval;
-        }
-
-        (ret_eval, best_move)
-    }
-    pub fn evaluate(&mut self) -> f32 {
+    // in centipawns, from the point of view of the side to move
+    pub fn evaluate(&mut self) -> Score {
         //evaluation criteria:
         // location of pieces on board
         // danger to king
         // danger to other pieces === potential to capture other pieces: but
         // weightage given should be lesser than the weightage given to the piece itself
         let mut score = 0.0;
-        let mut pos = 0;
-        for piece in self.squares.iter() {
-            if piece.is_some() {
-                let p = &piece.unwrap();
-                let s = (get_positional_weight(pos, p) + get_piece_weight(p))
-                    * piece.unwrap().get_color().get_value() as f32;
-                score += s;
-                // println!("{}", s);
-            }
-            pos += 1;
+        for pos in squares(self.occupied()) {
+            let p = &self.get_piece(pos).unwrap();
+            let s = (get_positional_weight(pos, p) + get_piece_weight(p))
+                * p.get_color().get_value() as f32;
+            score += s;
         }
         for mv in all_possible_valid_moves(self).iter() {
-            let tentative_piece = self.get_piece(decode_move(&mv).1);
-            if tentative_piece.is_some() {
-                score -= get_piece_weight(&tentative_piece.unwrap())
-                    * tentative_piece.unwrap().get_color().get_value() as f32;
+            if let Some(tentative_piece) = self.get_piece(mv.to()) {
+                score -= get_piece_weight(&tentative_piece)
+                    * tentative_piece.get_color().get_value() as f32;
             }
         }
-        score
+        let centipawns = ((score * 100.0).round() as Score).clamp(-MAX_EVAL, MAX_EVAL);
+        centipawns * self.side_to_move.get_value()
     }
     pub fn to_fen(&self) -> String {
         let mut fen = String::new();
-        for rank in 0..8 {
+        for rank in (0..8).rev() {
             let mut empty_squares = 0;
             for file in 0..8 {
-                let piece = self.get_piece(rank * 8 + file);
+                let piece = self.get_piece(Square::from_coords(file, rank).unwrap());
                 match piece {
                     Some(p) => {
                         if empty_squares > 0 {
@@ -244,61 +448,44 @@ impl Board {
             if empty_squares > 0 {
                 fen.push_str(&empty_squares.to_string());
             }
-            if rank < 7 {
-                fen.push_str("/");
+            if rank > 0 {
+                fen.push('/');
             }
         }
-        fen.push_str(" ");
+        fen.push(' ');
         if self.side_to_move == PieceColor::WHITE {
-            fen.push_str("w");
+            fen.push('w');
         } else {
-            fen.push_str("b");
+            fen.push('b');
         }
-        fen.push_str(" ");
-        if self.castling_rights & 0b0001 != 0 {
-            fen.push_str("K");
+        fen.push(' ');
+        if self.castling_rights & WHITE_KINGSIDE != 0 {
+            fen.push('K');
         }
-        if self.castling_rights & 0b0010 != 0 {
-            fen.push_str("Q");
+        if self.castling_rights & WHITE_QUEENSIDE != 0 {
+            fen.push('Q');
         }
-        if self.castling_rights & 0b0100 != 0 {
-            fen.push_str("k");
+        if self.castling_rights & BLACK_KINGSIDE != 0 {
+            fen.push('k');
         }
-        if self.castling_rights & 0b1000 != 0 {
-            fen.push_str("q");
+        if self.castling_rights & BLACK_QUEENSIDE != 0 {
+            fen.push('q');
         }
-        fen.push_str(" ");
-        if self.en_passant_square.is_some() {
-            fen.push_str(&pos_as_string(&self.en_passant_square.unwrap()));
+        if self.castling_rights == 0 {
+            fen.push('-');
+        }
+        fen.push(' ');
+        if let Some(ep) = self.en_passant_square {
+            fen.push_str(&ep.to_string());
         } else {
-            fen.push_str("-");
+            fen.push('-');
         }
-        fen.push_str(" ");
+        fen.push(' ');
         fen.push_str(&self.halfmove_clock.to_string());
-        fen.push_str(" ");
+        fen.push(' ');
         fen.push_str(&self.fullmove_number.to_string());
         fen
     }
-    pub fn plot(&self, positions: Vec<Position>) {
-        print_positions(&positions);
-        let mut board_string = String::new();
-        let files = "   a b c d e f g h\n";
-        board_string.push_str(files);
-        for rank in 0..8 {
-            board_string.push_str(&format!("{}| ", 8 - rank));
-            for file in 0..8 {
-                if positions.contains(&(rank * 8 + file)) {
-                    board_string.push_str("* ");
-                } else {
-                    board_string.push_str("  ");
-                }
-            }
-            board_string.push_str("\n");
-        }
-        board_string.push_str(files);
-
-        println!("{}", board_string);
-    }
 }
 
 impl fmt::Display for Board {
@@ -309,16 +496,16 @@ impl fmt::Display for Board {
         let divider = "-".repeat(20) + "\n";
         board_string.push_str(divider.as_str());
 
-        for rank in 0..8 {
-            board_string.push_str(&format!("{}| ", 8 - rank));
+        for rank in (0..8).rev() {
+            board_string.push_str(&format!("{}| ", rank + 1));
             for file in 0..8 {
-                let piece = self.get_piece(rank * 8 + file);
+                let piece = self.get_piece(Square::from_coords(file, rank).unwrap());
                 match piece {
                     Some(p) => board_string.push_str(&format!("{} ", p)),
                     None => board_string.push_str("* "),
                 }
             }
-            board_string.push_str("\n");
+            board_string.push('\n');
         }
         board_string.push_str(divider.as_str());
         board_string.push_str(files);
@@ -326,147 +513,392 @@ impl fmt::Display for Board {
     }
 }
 
+pub const START_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+// offsets are byte positions in the FEN string, pointing at the
+// offending character or the start of the offending field
+#[derive(Debug, Clone, PartialEq)]
+pub enum FenError {
+    Empty,
+    BadPieceChar { offset: usize, found: char },
+    // `rank` is the rank as written in the FEN, 8 for the first one
+    WrongRankLength { offset: usize, rank: u8 },
+    TooManyRanks { offset: usize },
+    TooFewRanks { offset: usize },
+    BadSide { offset: usize },
+    BadCastling { offset: usize },
+    BadEnPassant { offset: usize },
+    BadHalfmoveClock { offset: usize },
+    BadFullmoveNumber { offset: usize },
+    TooManyFields { offset: usize },
+    // the FEN parsed but does not describe a legal position
+    InvalidPosition(String),
+}
+
+impl FenError {
+    pub fn offset(&self) -> Option<usize> {
+        match self {
+            FenError::Empty | FenError::InvalidPosition(_) => None,
+            FenError::BadPieceChar { offset, .. }
+            | FenError::WrongRankLength { offset, .. }
+            | FenError::TooManyRanks { offset }
+            | FenError::TooFewRanks { offset }
+            | FenError::BadSide { offset }
+            | FenError::BadCastling { offset }
+            | FenError::BadEnPassant { offset }
+            | FenError::BadHalfmoveClock { offset }
+            | FenError::BadFullmoveNumber { offset }
+            | FenError::TooManyFields { offset } => Some(*offset),
+        }
+    }
+}
+
+impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let msg = match self {
+            FenError::Empty => "empty FEN string".to_string(),
+            FenError::BadPieceChar { found, .. } => format!("invalid piece character '{}'", found),
+            FenError::WrongRankLength { rank, .. } => {
+                format!("rank {} does not have exactly 8 squares", rank)
+            }
+            FenError::TooManyRanks { .. } => "more than 8 ranks".to_string(),
+            FenError::TooFewRanks { .. } => "fewer than 8 ranks".to_string(),
+            FenError::BadSide { .. } => "side to move must be 'w' or 'b'".to_string(),
+            FenError::BadCastling { .. } => {
+                "castling rights must be '-' or a subset of 'KQkq'".to_string()
+            }
+            FenError::BadEnPassant { .. } => {
+                "en passant square must be '-' or a square on rank 3 or 6".to_string()
+            }
+            FenError::BadHalfmoveClock { .. } => {
+                "halfmove clock must be a non-negative number".to_string()
+            }
+            FenError::BadFullmoveNumber { .. } => {
+                "fullmove number must be a positive number".to_string()
+            }
+            FenError::TooManyFields { .. } => "more than 6 fields".to_string(),
+            FenError::InvalidPosition(msg) => format!("invalid position: {}", msg),
+        };
+        match self.offset() {
+            Some(offset) => write!(f, "Invalid FEN string: {} (at byte {})", msg, offset),
+            None => write!(f, "Invalid FEN string: {}", msg),
+        }
+    }
+}
+
 // Sample FEN: "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"
-pub fn create_board(fen: &str) -> Option<Board> {
+// only the piece placement is required, missing fields get their defaults.
+// without a castling field, every right whose king and rook are on their
+// starting squares is assumed
+pub fn create_board(fen: &str) -> Result<Board, FenError> {
     let mut board = Board {
-        squares: [None; 64],
-        piecemap: HashMap::new(),
+        pieces: [EMPTY; 6],
+        colors: [EMPTY; 2],
         side_to_move: PieceColor::WHITE,
-        castling_rights: 0b1111,
+        castling_rights: 0,
         en_passant_square: None,
         halfmove_clock: 0,
-        fullmove_number: 0,
+        fullmove_number: 1,
+        hash: 0,
     };
-    let info_array: Vec<&str> = fen.split(" ").collect();
-    populate_pieces(&mut board, info_array[0]);
-    for i in 1..info_array.len() {
+    // (byte offset, field)
+    let info_array: Vec<(usize, &str)> = fen
+        .split_ascii_whitespace()
+        .map(|field| (field.as_ptr() as usize - fen.as_ptr() as usize, field))
+        .collect();
+    if info_array.is_empty() {
+        return Err(FenError::Empty);
+    }
+    populate_pieces(&mut board, info_array[0].1, info_array[0].0)?;
+    for (i, &(offset, field)) in info_array.iter().enumerate().skip(1) {
         match i {
-            1 => {
-                if info_array[i] == "w" {
-                    board.side_to_move = PieceColor::WHITE;
-                } else {
-                    board.side_to_move = PieceColor::BLACK;
-                }
-            }
+            1 => match field {
+                "w" => board.side_to_move = PieceColor::WHITE,
+                "b" => board.side_to_move = PieceColor::BLACK,
+                _ => return Err(FenError::BadSide { offset }),
+            },
             2 => {
-                for c in info_array[i].chars() {
-                    match c {
-                        'K' => board.castling_rights |= 0b0001,
-                        'Q' => board.castling_rights |= 0b0010,
-                        'k' => board.castling_rights |= 0b0100,
-                        'q' => board.castling_rights |= 0b1000,
-                        _ => (),
+                if field == "-" {
+                    continue;
+                }
+                for (j, c) in field.char_indices() {
+                    let right = match c {
+                        'K' => WHITE_KINGSIDE,
+                        'Q' => WHITE_QUEENSIDE,
+                        'k' => BLACK_KINGSIDE,
+                        'q' => BLACK_QUEENSIDE,
+                        _ => 0,
+                    };
+                    if right == 0 || board.castling_rights & right != 0 {
+                        return Err(FenError::BadCastling { offset: offset + j });
                     }
+                    board.castling_rights |= right;
                 }
             }
             3 => {
-                if info_array[i] == "-" {
+                if field == "-" {
                     board.en_passant_square = None;
-                } else {
-                    if info_array[i].len() != 2 {
-                        println!("Invalid FEN string: invalid en passant square"); //todo: respond to client with actual error msg
-                        return None;
+                    continue;
+                }
+                match field.parse::<Square>() {
+                    Ok(sq) if sq.rank() == 2 || sq.rank() == 5 => {
+                        board.en_passant_square = Some(sq)
                     }
-                    let file = info_array[i].chars().nth(0).unwrap() as u8 - 'a' as u8;
-                    let rank = info_array[i].chars().nth(1).unwrap() as u8 - '1' as u8;
-                    board.en_passant_square = Some(rank * 8 + file as Position);
+                    _ => return Err(FenError::BadEnPassant { offset }),
                 }
             }
-            4 => board.halfmove_clock = info_array[i].parse::<u8>().unwrap(),
-            5 => board.fullmove_number = info_array[i].parse::<u8>().unwrap(),
-            _ => {
-                println!("Invalid FEN string: too many fields");
-                return None;
-            }
+            4 => match field.parse::<u16>() {
+                Ok(n) => board.halfmove_clock = n,
+                Err(_) => return Err(FenError::BadHalfmoveClock { offset }),
+            },
+            5 => match field.parse::<u16>() {
+                Ok(n) if n > 0 => board.fullmove_number = n,
+                _ => return Err(FenError::BadFullmoveNumber { offset }),
+            },
+            _ => return Err(FenError::TooManyFields { offset }),
         }
     }
-    if board.validate().is_err() {
-        return None;
+    if info_array.len() < 3 {
+        board.castling_rights = board.castling_rights_from_placement();
     }
-    Some(board)
+    board.validate().map_err(FenError::InvalidPosition)?;
+    board.hash = board.compute_hash();
+    Ok(board)
 }
 
-pub fn populate_pieces(board: &mut Board, piece_placement: &str) -> Result<(), String> {
-    let ranks: Vec<&str> = piece_placement.split("/").collect();
+// `offset` is the byte offset of `piece_placement` in the FEN, so that
+// errors point into the whole string
+pub fn populate_pieces(
+    board: &mut Board,
+    piece_placement: &str,
+    offset: usize,
+) -> Result<(), FenError> {
     let mut rank = 0;
-    for r in ranks {
-        let mut file = 0;
-        for c in r.chars() {
-            if c.is_digit(10) {
-                file += c.to_digit(10).unwrap() as usize;
-            } else {
-                let piece = match c {
-                    'p' => Some(Piece {
-                        color: PieceColor::BLACK,
-                        piece_type: PieceType::PAWN,
-                    }),
-                    'n' => Some(Piece {
-                        color: PieceColor::BLACK,
-                        piece_type: PieceType::KNIGHT,
-                    }),
-                    'b' => Some(Piece {
-                        color: PieceColor::BLACK,
-                        piece_type: PieceType::BISHOP,
-                    }),
-                    'r' => Some(Piece {
-                        color: PieceColor::BLACK,
-                        piece_type: PieceType::ROOK,
-                    }),
-                    'q' => Some(Piece {
-                        color: PieceColor::BLACK,
-                        piece_type: PieceType::QUEEN,
-                    }),
-                    'k' => Some(Piece {
-                        color: PieceColor::BLACK,
-                        piece_type: PieceType::KING,
-                    }),
-                    'P' => Some(Piece {
-                        color: PieceColor::WHITE,
-                        piece_type: PieceType::PAWN,
-                    }),
-                    'N' => Some(Piece {
-                        color: PieceColor::WHITE,
-                        piece_type: PieceType::KNIGHT,
-                    }),
-                    'B' => Some(Piece {
-                        color: PieceColor::WHITE,
-                        piece_type: PieceType::BISHOP,
-                    }),
-                    'R' => Some(Piece {
-                        color: PieceColor::WHITE,
-                        piece_type: PieceType::ROOK,
-                    }),
-                    'Q' => Some(Piece {
-                        color: PieceColor::WHITE,
-                        piece_type: PieceType::QUEEN,
-                    }),
-                    'K' => Some(Piece {
-                        color: PieceColor::WHITE,
-                        piece_type: PieceType::KING,
-                    }),
-                    _ => return Err("Invalid FEN string: invalid piece type".into()),
-                };
-                board.set_piece(rank * 8 + file as Position, piece);
-                file += 1;
+    let mut file = 0;
+    for (i, c) in piece_placement.char_indices() {
+        let offset = offset + i;
+        if c == '/' {
+            if file != 8 {
+                return Err(FenError::WrongRankLength {
+                    offset,
+                    rank: 8 - rank,
+                });
+            }
+            rank += 1;
+            file = 0;
+            if rank == 8 {
+                return Err(FenError::TooManyRanks { offset });
+            }
+            continue;
+        }
+        if let Some(d) = c.to_digit(10) {
+            if d == 0 || d == 9 || file + d as u8 > 8 {
+                return Err(FenError::WrongRankLength {
+                    offset,
+                    rank: 8 - rank,
+                });
             }
+            file += d as u8;
+            continue;
         }
-        rank += 1;
+        let color = if c.is_ascii_uppercase() {
+            PieceColor::WHITE
+        } else {
+            PieceColor::BLACK
+        };
+        let piece_type = match c.to_ascii_lowercase() {
+            'p' => PieceType::PAWN,
+            'n' => PieceType::KNIGHT,
+            'b' => PieceType::BISHOP,
+            'r' => PieceType::ROOK,
+            'q' => PieceType::QUEEN,
+            'k' => PieceType::KING,
+            _ => return Err(FenError::BadPieceChar { offset, found: c }),
+        };
+        if file == 8 {
+            return Err(FenError::WrongRankLength {
+                offset,
+                rank: 8 - rank,
+            });
+        }
+        // `rank` counts from the top of the board, as the FEN lists ranks
+        let square = Square::from_coords(file, 7 - rank).unwrap();
+        board.set_piece(square, Some(Piece { color, piece_type }));
+        file += 1;
+    }
+    let end = offset + piece_placement.len();
+    if file != 8 {
+        return Err(FenError::WrongRankLength {
+            offset: end,
+            rank: 8 - rank,
+        });
+    }
+    if rank < 7 {
+        return Err(FenError::TooFewRanks { offset: end });
     }
     Ok(())
 }
 
-pub fn print_moves(moves: &Vec<Move>) {
-    println!(
-        "Possible moves: {:?}",
-        moves.iter().map(|m| move_as_string(&m)).collect::<Vec<_>>()
-    );
-}
-pub fn print_positions(positions: &Vec<Position>) {
-    println!(
-        "Positions: {:?}",
-        positions
-            .iter()
-            .map(|n| pos_as_string(&n))
-            .collect::<Vec<_>>()
-    );
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_castling_field_follows_the_placement() {
+        let mut board = create_board("4k3/8/8/8/8/8/8/4K3").unwrap();
+        assert_eq!(board.castling_rights, 0);
+        assert_eq!(board.perft(1), 5);
+        let board = create_board("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w").unwrap();
+        assert_eq!(board.castling_rights, 0b1111);
+        let board = create_board("r3k3/8/8/8/8/8/8/4K2R b").unwrap();
+        assert_eq!(board.castling_rights, WHITE_KINGSIDE | BLACK_QUEENSIDE);
+        assert_eq!(board.hash, board.compute_hash());
+    }
+
+    #[test]
+    fn explicit_castling_rights_must_match_the_placement() {
+        assert!(create_board("4k3/8/8/8/8/8/8/4K3 w - - 0 1").is_ok());
+        assert!(matches!(
+            create_board("4k3/8/8/8/8/8/8/4K3 w K - 0 1"),
+            Err(FenError::InvalidPosition(_))
+        ));
+    }
+
+    #[test]
+    fn illegal_positions_are_rejected() {
+        let cases = [
+            ("8/8/8/8/8/8/8/4K3 w - - 0 1", "missing black king"),
+            ("4k3/8/8/8/8/8/8/K3K3 w - - 0 1", "white has 2 kings"),
+            (
+                "4k3/8/8/8/8/NNNNNNNN/PPPPPPPP/4K3 w - - 0 1",
+                "white has more than 16 pieces",
+            ),
+            ("4k3/8/8/8/8/P7/PPPPPPPP/4K3 w - - 0 1", "white has 9 pawns"),
+            (
+                "4k3/8/8/8/8/QQ6/PPPPPPPP/4K3 w - - 0 1",
+                "white has 8 pawns and at least 1 promoted pieces",
+            ),
+            (
+                "P3k3/8/8/8/8/8/8/4K3 w - - 0 1",
+                "pawn on the first or eighth rank",
+            ),
+            (
+                "4k3/8/8/8/8/8/8/p3K3 w - - 0 1",
+                "pawn on the first or eighth rank",
+            ),
+            ("8/8/8/8/8/8/3k4/4K3 w - - 0 1", "kings are adjacent"),
+            (
+                "4k3/8/8/8/8/8/8/4RK2 w - - 0 1",
+                "the side not to move is in check",
+            ),
+            (
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq e3 0 1",
+                "en passant square e3 does not follow a double pawn push",
+            ),
+            (
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR b KQkq e3 0 1",
+                "en passant square e3 does not follow a double pawn push",
+            ),
+        ];
+        for (fen, reason) in cases {
+            assert_eq!(
+                create_board(fen).err(),
+                Some(FenError::InvalidPosition(reason.to_string())),
+                "{}",
+                fen
+            );
+        }
+        assert!(
+            create_board("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1").is_ok()
+        );
+    }
+
+    // the piece placement of the start position, 43 bytes long
+    const PLACEMENT: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR";
+
+    #[test]
+    fn fen_errors_point_at_the_problem() {
+        let cases = [
+            ("", FenError::Empty, None),
+            ("  ", FenError::Empty, None),
+            (
+                "é/8/8/8/8/8/8/8",
+                FenError::BadPieceChar {
+                    offset: 0,
+                    found: 'é',
+                },
+                Some(0),
+            ),
+            (
+                "rnbqkbnr/ppppéppp/8/8/8/8/PPPPPPPP/RNBQKBNR",
+                FenError::BadPieceChar {
+                    offset: 13,
+                    found: 'é',
+                },
+                Some(13),
+            ),
+            (
+                "rnbqkbnr/ppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR",
+                FenError::WrongRankLength {
+                    offset: 16,
+                    rank: 7,
+                },
+                Some(16),
+            ),
+            (
+                "rnbqkbnrp/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR",
+                FenError::WrongRankLength { offset: 8, rank: 8 },
+                Some(8),
+            ),
+            (
+                "9/8/8/8/8/8/8/8",
+                FenError::WrongRankLength { offset: 0, rank: 8 },
+                Some(0),
+            ),
+            (
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBN",
+                FenError::WrongRankLength {
+                    offset: 42,
+                    rank: 1,
+                },
+                Some(42),
+            ),
+            (
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR/8",
+                FenError::TooManyRanks { offset: 43 },
+                Some(43),
+            ),
+            (
+                "8/8/8/8/8/8/8",
+                FenError::TooFewRanks { offset: 13 },
+                Some(13),
+            ),
+        ];
+        let fields = [
+            (" x", FenError::BadSide { offset: 44 }),
+            (" w KKkq", FenError::BadCastling { offset: 47 }),
+            (" w KQxq", FenError::BadCastling { offset: 48 }),
+            (" w KQkq e4", FenError::BadEnPassant { offset: 51 }),
+            (" w KQkq z9", FenError::BadEnPassant { offset: 51 }),
+            (" w KQkq - -1", FenError::BadHalfmoveClock { offset: 53 }),
+            (" w KQkq - 0 0", FenError::BadFullmoveNumber { offset: 55 }),
+            (" w KQkq - 0 1 x", FenError::TooManyFields { offset: 57 }),
+        ];
+        let cases = cases
+            .into_iter()
+            .map(|(fen, error, offset)| (fen.to_string(), error, offset))
+            .chain(fields.into_iter().map(|(rest, error)| {
+                let offset = error.offset();
+                (format!("{}{}", PLACEMENT, rest), error, offset)
+            }));
+        for (fen, error, offset) in cases {
+            let found = create_board(&fen).unwrap_err();
+            assert_eq!(found, error, "{}", fen);
+            assert_eq!(found.offset(), offset, "{}", fen);
+        }
+        // a FEN that parses but isn't a legal position has no offset
+        let found = create_board("8/8/8/8/8/8/8/8").unwrap_err();
+        assert!(matches!(found, FenError::InvalidPosition(_)));
+        assert_eq!(found.offset(), None);
+    }
 }
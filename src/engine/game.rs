@@ -0,0 +1,207 @@
+use std::fmt;
+
+use super::{
+    board::{create_board, Board, FenError, MoveContext, START_FEN},
+    moves::match_valid_move,
+    san::SanError,
+    search::{SearchLimits, SearchResult},
+    status::GameStatus,
+    tt::TranspositionTable,
+    Move,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GameError {
+    BadFen(FenError),
+    // `ply` counts the moves played before it, from 0
+    IllegalMove { ply: usize, found: Move },
+}
+
+impl fmt::Display for GameError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GameError::BadFen(e) => write!(f, "{}", e),
+            GameError::IllegalMove { ply, found } => {
+                write!(f, "illegal move {} at ply {}", found, ply)
+            }
+        }
+    }
+}
+
+/*
+A game from some starting position: the current board plus every move
+played to reach it. For each move the hash of the position it was played
+from and its MoveContext are kept, so moves can be taken back and
+repetitions counted.
+ */
+pub struct Game {
+    start_fen: String,
+    board: Board,
+    moves: Vec<Move>,
+    hashes: Vec<u64>,
+    contexts: Vec<MoveContext>,
+}
+
+impl Game {
+    pub fn new() -> Game {
+        Game::from_fen(START_FEN).expect("start position must be valid")
+    }
+    pub fn from_fen(fen: &str) -> Result<Game, FenError> {
+        Ok(Game {
+            start_fen: fen.to_string(),
+            board: create_board(fen)?,
+            moves: Vec::new(),
+            hashes: Vec::new(),
+            contexts: Vec::new(),
+        })
+    }
+    // plays `moves` from `fen`, stopping at the first illegal one
+    pub fn replay(fen: &str, moves: &[Move]) -> Result<Game, GameError> {
+        let mut game = Game::from_fen(fen).map_err(GameError::BadFen)?;
+        for m in moves {
+            game.push_move(*m)?;
+        }
+        Ok(game)
+    }
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+    pub fn start_fen(&self) -> &str {
+        &self.start_fen
+    }
+    pub fn moves(&self) -> &[Move] {
+        &self.moves
+    }
+    /*
+    plays `m` if it is legal. only its squares and promotion piece have to
+    match, so a move parsed from UCI is enough; the fully flagged move that
+    was played is returned
+     */
+    pub fn push_move(&mut self, m: Move) -> Result<Move, GameError> {
+        let legal = match_valid_move(&mut self.board, &m).ok_or(GameError::IllegalMove {
+            ply: self.moves.len(),
+            found: m,
+        })?;
+        self.play(legal);
+        Ok(legal)
+    }
+    // plays a move written in SAN, see Board::parse_san for what is accepted
+    pub fn push_san(&mut self, san: &str) -> Result<Move, SanError> {
+        let m = self.board.parse_san(san)?;
+        self.play(m);
+        Ok(m)
+    }
+    // `m` must be legal
+    fn play(&mut self, m: Move) {
+        self.hashes.push(self.board.hash);
+        self.contexts.push(self.board.make_move(m));
+        self.moves.push(m);
+    }
+    // takes back the last move, if there is one. the CLI has no takebacks
+    #[allow(dead_code)]
+    pub fn pop_move(&mut self) -> Option<Move> {
+        let ctx = self.contexts.pop()?;
+        self.board.unmake_move(ctx);
+        self.hashes.pop();
+        self.moves.pop()
+    }
+    // how many times the current position has occurred, counting this time
+    pub fn repetition_count(&self) -> usize {
+        self.board.repetition_count(&self.hashes)
+    }
+    pub fn status(&mut self) -> GameStatus {
+        self.board.status(&self.hashes)
+    }
+    pub fn search(&mut self, limits: &SearchLimits, tt: &mut TranspositionTable) -> SearchResult {
+        self.board.search(limits, tt, &self.hashes)
+    }
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Game::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::search::DRAW_SCORE;
+
+    fn uci(moves: &[&str]) -> Vec<Move> {
+        moves.iter().map(|m| m.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn pop_move_takes_back_push_move() {
+        let mut game = Game::new();
+        let start = game.board().clone();
+        // castling and an en passant capture, to restore the rights and target
+        let moves = uci(&[
+            "e2e4", "g8f6", "e4e5", "d7d5", "e5d6", "e7e6", "g1f3", "f8d6", "f1c4", "e8g8", "e1g1",
+        ]);
+        let mut boards = Vec::new();
+        for m in moves.iter() {
+            boards.push(game.board().clone());
+            game.push_move(*m).unwrap();
+        }
+        assert_eq!(game.moves().len(), moves.len());
+        while let Some(m) = game.pop_move() {
+            assert_eq!(game.board(), &boards.pop().unwrap(), "taking back {}", m);
+        }
+        assert_eq!(game.board(), &start);
+        assert!(game.moves().is_empty());
+    }
+
+    #[test]
+    fn replay_stops_at_the_first_illegal_move() {
+        let game = Game::replay(START_FEN, &uci(&["d2d4", "d7d5", "c2c4"])).unwrap();
+        assert_eq!(
+            game.board().to_fen(),
+            "rnbqkbnr/ppp1pppp/8/3p4/2PP4/8/PP2PPPP/RNBQKBNR b KQkq c3 0 2"
+        );
+        assert_eq!(
+            Game::replay(START_FEN, &uci(&["e2e4", "e7e5", "e4e5"])).err(),
+            Some(GameError::IllegalMove {
+                ply: 2,
+                found: "e4e5".parse().unwrap()
+            })
+        );
+        assert!(matches!(
+            Game::replay("8/8/8/8 w - -", &[]),
+            Err(GameError::BadFen(_))
+        ));
+    }
+
+    #[test]
+    fn repetitions_are_counted() {
+        let shuffle = ["g1f3", "g8f6", "f3g1", "f6g8"];
+        let mut game = Game::new();
+        for (i, m) in shuffle.iter().cycle().take(8).enumerate() {
+            assert_eq!(game.repetition_count(), 1 + i / 4);
+            game.push_move(m.parse().unwrap()).unwrap();
+        }
+        assert_eq!(game.repetition_count(), 3);
+        assert_eq!(game.status(), GameStatus::THREEFOLD_REPETITION);
+        game.pop_move();
+        assert_eq!(game.status(), GameStatus::ONGOING);
+    }
+
+    // a queen down, white can only hold the draw by repeating the position
+    #[test]
+    fn search_takes_a_repetition_draw() {
+        let fen = "kq6/8/8/8/8/8/8/7K w - - 0 1";
+        let mut game = Game::replay(fen, &uci(&["h1g1", "b8c8", "g1h1", "c8b8"])).unwrap();
+        let mut lost = create_board(&game.board().to_fen()).unwrap();
+        let limits = SearchLimits {
+            depth: Some(2),
+            ..Default::default()
+        };
+        let result = lost.search(&limits, &mut TranspositionTable::default(), &[]);
+        assert!(result.score < -500, "{}", result);
+
+        let result = game.search(&limits, &mut TranspositionTable::default());
+        assert_eq!(result.best_move, "h1g1".parse().ok());
+        assert_eq!(result.score, DRAW_SCORE);
+    }
+}
@@ -1,52 +1,117 @@
-use crate::engine::board::pos_as_string;
+use std::{fmt, str::FromStr};
 
-use self::board::Position;
+use self::{piece::PieceType, square::Square};
 
+pub mod attacks;
+pub mod bitboard;
 pub mod board;
+pub mod game;
 pub mod moves;
+pub mod ordering;
+pub mod perft;
+pub mod pgn;
 pub mod piece;
+pub mod prng;
+pub mod san;
+pub mod search;
+pub mod square;
+pub mod status;
+pub mod tt;
 pub mod weights;
+pub mod zobrist;
 
+pub const QUIET_MOVE: u16 = 0b0000;
+pub const DOUBLE_PAWN_PUSH: u16 = 0b0001;
+pub const KING_CASTLE: u16 = 0b0010;
+pub const QUEEN_CASTLE: u16 = 0b0011;
+pub const CAPTURE: u16 = 0b0100;
+pub const EN_PASSANT_CAPTURE: u16 = 0b0101;
+// promotions have the high flag bit set, and may be combined with CAPTURE
+pub const KNIGHT_PROMOTION: u16 = 0b1000;
+pub const BISHOP_PROMOTION: u16 = 0b1001;
+pub const ROOK_PROMOTION: u16 = 0b1010;
+pub const QUEEN_PROMOTION: u16 = 0b1011;
 
-pub type Move= u16;
-
+pub const PROMOTIONS: [u16; 4] = [
+    QUEEN_PROMOTION,
+    ROOK_PROMOTION,
+    BISHOP_PROMOTION,
+    KNIGHT_PROMOTION,
+];
 
 /*
-@returns a tuple of (from,to)    
+bits 0-5: destination square
+bits 6-11: source square
+bits 12-15: flags
  */
-pub fn decode_move(m: &Move) -> (Position, Position) {
-    let from = (m >> 6) as u8;
-    let to = (m & 0b111111) as u8;
-    (from, to)
-}
-pub fn encode_move(from: Position, to: Position) -> Move {
-    ((from as u16) << 6) | (to as u16)
-}
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Move(u16);
 
-pub fn move_as_string(m: &Move) -> String {
-    let (from, to) = decode_move(m);
-    format!("{}{}", pos_as_string(&from), pos_as_string(&to))
-}
-
-
-pub fn parse_move(m: &str) -> Result<Move, String> {
-    if m.len() != 4 {
-        return Err("invalid move string".to_string());
+impl Move {
+    pub fn new(from: Square, to: Square, flags: u16) -> Move {
+        debug_assert!(flags < 16, "move flags out of range: {}", flags);
+        Move((flags << 12) | ((from.index() as u16) << 6) | (to.index() as u16))
+    }
+    pub fn from(self) -> Square {
+        Square::from_index(((self.0 >> 6) & 0b111111) as u8)
     }
-    let from = parse_pos(&m[0..2])?;
-    let to = parse_pos(&m[2..4])?;
-    if from >= 64 || to >= 64 {
-        return Err("invalid move".to_string());
+    pub fn to(self) -> Square {
+        Square::from_index((self.0 & 0b111111) as u8)
+    }
+    pub fn flags(self) -> u16 {
+        self.0 >> 12
+    }
+    pub fn is_capture(self) -> bool {
+        self.flags() & CAPTURE != 0
+    }
+    pub fn promotion_piece(self) -> Option<PieceType> {
+        let flags = self.flags();
+        if flags & 0b1000 == 0 {
+            return None;
+        }
+        match flags & 0b1011 {
+            KNIGHT_PROMOTION => Some(PieceType::KNIGHT),
+            BISHOP_PROMOTION => Some(PieceType::BISHOP),
+            ROOK_PROMOTION => Some(PieceType::ROOK),
+            _ => Some(PieceType::QUEEN),
+        }
     }
-    Ok(encode_move(from, to))
 }
 
-pub fn parse_pos(m: &str) -> Result<Position, String> {
-    let f=m.chars().nth(0).unwrap();
-    let r=m.chars().nth(1).unwrap();
-    if f < 'a' || f > 'h' || r < '1' || r > '8' {
-        return Err("invalid position".to_string());
+// UCI notation, e.g. `e2e4` or `e7e8q`
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let promotion = match self.promotion_piece() {
+            Some(PieceType::KNIGHT) => "n",
+            Some(PieceType::BISHOP) => "b",
+            Some(PieceType::ROOK) => "r",
+            Some(_) => "q",
+            None => "",
+        };
+        write!(f, "{}{}{}", self.from(), self.to(), promotion)
     }
-    Ok(board::encode_pos(7-(r as u8 - '1' as u8), f as u8 - 'a' as u8))
 }
 
+/*
+parses a UCI move like `e2e4` or `e7e8q`. only the promotion flag is
+set, use moves::match_valid_move to get the fully flagged move
+ */
+impl FromStr for Move {
+    type Err = String;
+    fn from_str(m: &str) -> Result<Move, String> {
+        if !m.is_ascii() || (m.len() != 4 && m.len() != 5) {
+            return Err("invalid move string".to_string());
+        }
+        let from = m[0..2].parse::<Square>()?;
+        let to = m[2..4].parse::<Square>()?;
+        let flags = match m.chars().nth(4) {
+            None => QUIET_MOVE,
+            Some('q') => QUEEN_PROMOTION,
+            Some('r') => ROOK_PROMOTION,
+            Some('b') => BISHOP_PROMOTION,
+            Some('n') => KNIGHT_PROMOTION,
+            Some(_) => return Err("invalid promotion piece".to_string()),
+        };
+        Ok(Move::new(from, to, flags))
+    }
+}
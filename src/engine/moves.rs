@@ -1,227 +1,112 @@
-use crate::engine::board::pos_as_string;
-
 use super::{
-    board::{decode_pos, encode_pos, print_moves, Board, Position},
-    decode_move, encode_move,
-    piece::{Piece, PieceColor, PieceType},
-    Move,
+    attacks::{
+        bishop_attacks, king_attacks, knight_attacks, pawn_attacks, queen_attacks, rook_attacks,
+    },
+    bitboard::{is_set, squares, Bitboard, EMPTY, RANK_1, RANK_8},
+    board::{Board, BLACK_KINGSIDE, BLACK_QUEENSIDE, WHITE_KINGSIDE, WHITE_QUEENSIDE},
+    piece::{Piece, PieceColor, PieceType, PIECE_TYPES},
+    square::Square,
+    Move, CAPTURE, DOUBLE_PAWN_PUSH, EN_PASSANT_CAPTURE, KING_CASTLE, PROMOTIONS, QUEEN_CASTLE,
+    QUIET_MOVE,
 };
 
 //raw move means those moves are not excluded which can lead to the same side getting a check
+//the *_moves_raw functions return the target squares as a bitboard
 
-macro_rules! nth_bit {
-    ($n:expr, $bit:expr) => {
-        (((($n) >> ($bit)) & 1) as i8)
-    };
+fn own_pieces(board: &Board) -> Bitboard {
+    board.color_bb(board.side_to_move)
 }
 
-macro_rules! in_bounds {
-    ($r:expr, $f:expr) => {
-        ($r < 8 && $f < 8 && $r >= 0 && $f >= 0)
-    };
+pub fn slant_moves_raw(base: Square, board: &Board) -> Bitboard {
+    bishop_attacks(base, board.occupied()) & !own_pieces(board)
 }
 
-fn position_occupied(board: &Board, pos: &Position) -> bool {
-    let piece = board.get_piece(*pos);
-    return piece.is_some();
+pub fn rect_moves_raw(base: Square, board: &Board) -> Bitboard {
+    rook_attacks(base, board.occupied()) & !own_pieces(board)
 }
 
-fn is_opponent_piece_at(board: &Board, pos: &Position) -> bool {
-    let piece = board.get_piece(*pos);
-    if piece.is_some() {
-        return piece.unwrap().color != board.side_to_move;
-    }
-    return false;
+pub fn knight_moves_raw(base: Square, board: &Board) -> Bitboard {
+    knight_attacks(base) & !own_pieces(board)
 }
 
-pub fn slant_moves_raw(base: Position, board: &Board) -> Vec<Position> {
-    let mut moves: Vec<Position> = Vec::new();
-    let (r, f) = decode_pos(&base);
-    let mut dir = 0;
-    while dir <= 3 {
-        let coeff_1 = nth_bit!(dir, 0) * 2 - 1;
-        let coeff_2 = nth_bit!(dir, 1) * 2 - 1;
-        let mut i = 1;
-        loop {
-            let newx: i16 = (r as i8 + i * coeff_1) as i16;
-            let newy: i16 = (f as i8 + i * coeff_2) as i16;
-            if !in_bounds!(newx, newy) {
-                break;
-            }
-            let m = encode_pos(newx as u8, newy as u8);
-            if position_occupied(board, &m) {
-                if is_opponent_piece_at(board, &m) {
-                    moves.push(m);
-                }
-                break;
-            }
-            moves.push(m);
-            i += 1;
-        }
-        dir += 1;
-    }
-
-    moves
+pub fn king_moves_raw(base: Square, board: &Board) -> Bitboard {
+    (king_attacks(base) & !own_pieces(board)) | castling_moves_raw(base, board)
 }
 
-pub fn rect_moves_raw(base: Position, board: &Board) -> Vec<Position> {
-    let mut moves: Vec<Position> = Vec::new();
-    let (r, f) = decode_pos(&base);
-    let coeffs = [(1, 0), (0, 1), (-1, 0), (0, -1)];
-    let mut dir: usize = 0;
-    while dir <= 3 {
-        let coeff_1 = coeffs[dir].0;
-        let coeff_2 = coeffs[dir].1;
-        let mut i = 1;
-        loop {
-            let newx: i16 = r as i16 + i * coeff_1;
-            let newy: i16 = f as i16 + i * coeff_2;
-            // println!("{} {}", newx, newy);
-            if !in_bounds!(newx, newy) {
-                break;
-            }
-            let m = encode_pos(newx as u8, newy as u8);
-            if position_occupied(board, &m) {
-                if is_opponent_piece_at(board, &m) {
-                    moves.push(m);
-                }
-                break;
-            }
-            moves.push(m);
-
-            i += 1;
-        }
-        dir += 1;
-    }
-
-    moves
-}
-
-pub fn knight_moves_raw(base: Position, board: &Board) -> Vec<Position> {
-    let mut moves: Vec<Position> = Vec::new();
-    let (r, f) = decode_pos(&base);
-    let coeffs = [
-        (1, 2),
-        (2, 1),
-        (2, -1),
-        (1, -2),
-        (-1, -2),
-        (-2, -1),
-        (-2, 1),
-        (-1, 2),
-    ];
-    let mut dir: usize = 0;
-    while dir <= 7 {
-        let coeff_1 = coeffs[dir].0;
-        let coeff_2 = coeffs[dir].1;
-        let newx: i16 = r as i16 + coeff_1;
-        let newy: i16 = f as i16 + coeff_2;
-        if in_bounds!(newx, newy) {
-            let m = encode_pos(newx as u8, newy as u8);
-            let p = board.get_piece(m);
-            if p.is_none() || is_opponent_piece_at(board, &m) {
-                moves.push(m);
-            }
-        }
-        dir += 1;
+// castling is encoded as the king moving two squares towards the rook.
+// the king must not be in check or pass through an attacked square; landing
+// in check is caught later by filter_out_check_moves like any other move
+pub fn castling_moves_raw(base: Square, board: &Board) -> Bitboard {
+    let mut moves = EMPTY;
+    let color = board.side_to_move;
+    let (kingside, queenside, home) = match color {
+        PieceColor::WHITE => (WHITE_KINGSIDE, WHITE_QUEENSIDE, Square::E1),
+        PieceColor::BLACK => (BLACK_KINGSIDE, BLACK_QUEENSIDE, Square::E8),
+    };
+    if base != home || board.castling_rights & (kingside | queenside) == 0 {
+        return moves;
+    }
+    let opponent = color.opponent_color();
+    if find_in_raw_move_targets(board, &base, &opponent) {
+        return moves;
+    }
+    // the king is on its home square, so every file offset below is on the board
+    let along = |files: i8| base.offset(files, 0).unwrap();
+    let rooks = board.piece_bb(color, PieceType::ROOK);
+    let occupied = board.occupied();
+    if board.castling_rights & kingside != 0
+        && is_set(rooks, along(3))
+        && occupied & (along(1).bit() | along(2).bit()) == EMPTY
+        && !find_in_raw_move_targets(board, &along(1), &opponent)
+    {
+        moves |= along(2).bit();
     }
-
-    moves
-}
-
-pub fn king_moves_raw(base: Position, board: &Board) -> Vec<Position> {
-    let mut moves: Vec<Position> = Vec::new();
-    let (r, f) = decode_pos(&base);
-    let coeffs = [
-        (1, 0),
-        (0, 1),
-        (-1, 0),
-        (0, -1),
-        (1, 1),
-        (-1, -1),
-        (-1, 1),
-        (1, -1),
-    ];
-    let mut dir: usize = 0;
-    while dir <= 7 {
-        let coeff_1 = coeffs[dir].0;
-        let coeff_2 = coeffs[dir].1;
-        let newx: i16 = r as i16 + coeff_1;
-        let newy: i16 = f as i16 + coeff_2;
-        if in_bounds!(newx, newy) {
-            let m = encode_pos(newx as u8, newy as u8);
-            let p = board.get_piece(m);
-            if p.is_none() || is_opponent_piece_at(board, &m) {
-                moves.push(m);
-            }
-        }
-        dir += 1;
+    if board.castling_rights & queenside != 0
+        && is_set(rooks, along(-4))
+        && occupied & (along(-1).bit() | along(-2).bit() | along(-3).bit()) == EMPTY
+        && !find_in_raw_move_targets(board, &along(-1), &opponent)
+    {
+        moves |= along(-2).bit();
     }
 
     moves
 }
 
-pub fn pawn_moves_raw(base: Position, board: &Board) -> Vec<Position> {
+pub fn pawn_moves_raw(base: Square, board: &Board) -> Bitboard {
     let color = board.side_to_move;
-    let mut moves: Vec<Position> = Vec::new();
-    let (r, f) = decode_pos(&base);
-    let coeff = match color {
-        super::piece::PieceColor::WHITE => -1,
-        super::piece::PieceColor::BLACK => 1,
+    let empty = !board.occupied();
+    let (single, start_rank) = match color {
+        PieceColor::WHITE => ((base.bit() >> 8) & empty, 1),
+        PieceColor::BLACK => ((base.bit() << 8) & empty, 6),
     };
-    let newx: i16 = r as i16 + coeff;
-    let newy: i16 = f as i16;
-    if in_bounds!(newx, newy) {
-        let m = encode_pos(newx as u8, newy as u8);
-        let p = board.get_piece(m);
-        if p.is_none() {
-            moves.push(m);
-        }
+    let mut moves = single;
+    if single != EMPTY && base.rank() == start_rank {
+        moves |= match color {
+            PieceColor::WHITE => (single >> 8) & empty,
+            PieceColor::BLACK => (single << 8) & empty,
+        };
     }
-    if (r == 6 && color == super::piece::PieceColor::WHITE)
-        || (r == 1 && color == super::piece::PieceColor::BLACK)
-    {
-        let newx: i16 = r as i16 + 2 * coeff;
-        let newy: i16 = f as i16;
-        if in_bounds!(newx, newy) {
-            let m = encode_pos(newx as u8, newy as u8);
-            let p = board.get_piece(m);
-            if p.is_none() {
-                moves.push(m);
-            }
-        }
-    }
-    let coeffs = [(1, 1), (1, -1)];
-    for cof in coeffs {
-        let coeff_1 = cof.0;
-        let coeff_2 = cof.1;
-        let newx: i16 = r as i16 + coeff_1 * coeff;
-        let newy: i16 = f as i16 + coeff_2;
-        if in_bounds!(newx, newy) {
-            let m = encode_pos(newx as u8, newy as u8);
-            if is_opponent_piece_at(board, &m) {
-                moves.push(m);
-            }
-        }
+    // an en passant capture that exposes the king along the rank
+    // is rejected by filter_out_check_moves, since make_move
+    // removes the captured pawn before the check test
+    let mut targets = board.color_bb(color.opponent_color());
+    if let Some(ep) = board.en_passant_square {
+        targets |= ep.bit();
     }
-
-    moves
+    moves | (pawn_attacks(base, color) & targets)
 }
 
-pub fn rook_moves_raw(base: Position, board: &Board) -> Vec<Position> {
+pub fn rook_moves_raw(base: Square, board: &Board) -> Bitboard {
     rect_moves_raw(base, board)
 }
-pub fn bishop_moves_raw(base: Position, board: &Board) -> Vec<Position> {
+pub fn bishop_moves_raw(base: Square, board: &Board) -> Bitboard {
     slant_moves_raw(base, board)
 }
-pub fn queen_moves_raw(base: Position, board: &Board) -> Vec<Position> {
-    let mut moves: Vec<Position> = Vec::new();
-    moves.append(&mut slant_moves_raw(base, &board));
-    moves.append(&mut rect_moves_raw(base, &board));
-    moves
+pub fn queen_moves_raw(base: Square, board: &Board) -> Bitboard {
+    queen_attacks(base, board.occupied()) & !own_pieces(board)
 }
 
-pub fn get_raw_moves(p: &Piece, pos: &Position, board: &Board) -> Vec<Move> {
+pub fn get_raw_moves(p: &Piece, pos: &Square, board: &Board) -> Vec<Move> {
     let srcvec = match p.piece_type {
         PieceType::PAWN => pawn_moves_raw(*pos, board),
         PieceType::BISHOP => bishop_moves_raw(*pos, board),
@@ -230,7 +115,46 @@ pub fn get_raw_moves(p: &Piece, pos: &Position, board: &Board) -> Vec<Move> {
         PieceType::QUEEN => queen_moves_raw(*pos, board),
         PieceType::ROOK => rook_moves_raw(*pos, board),
     };
-    srcvec.iter().map(|dest| encode_move(*pos, *dest)).collect()
+    let mut moves: Vec<Move> = Vec::new();
+    for dest in squares(srcvec) {
+        let mut flags = if is_set(board.occupied(), dest) {
+            CAPTURE
+        } else {
+            QUIET_MOVE
+        };
+        match p.piece_type {
+            PieceType::PAWN => {
+                if board.en_passant_square == Some(dest) {
+                    flags = EN_PASSANT_CAPTURE;
+                } else if pos.rank().abs_diff(dest.rank()) == 2 {
+                    flags = DOUBLE_PAWN_PUSH;
+                } else if is_set(RANK_1 | RANK_8, dest) {
+                    for promotion in PROMOTIONS {
+                        moves.push(Move::new(*pos, dest, flags | promotion));
+                    }
+                    continue;
+                }
+            }
+            PieceType::KING => {
+                if Some(dest) == pos.offset(2, 0) {
+                    flags = KING_CASTLE;
+                } else if Some(dest) == pos.offset(-2, 0) {
+                    flags = QUEEN_CASTLE;
+                }
+            }
+            _ => {}
+        }
+        moves.push(Move::new(*pos, dest, flags));
+    }
+    moves
+}
+
+// finds the valid move with the same squares and promotion piece as `m`,
+// which is how moves parsed from UCI strings get their remaining flags
+pub fn match_valid_move(board: &mut Board, m: &Move) -> Option<Move> {
+    all_possible_valid_moves(board).into_iter().find(|v| {
+        v.from() == m.from() && v.to() == m.to() && v.promotion_piece() == m.promotion_piece()
+    })
 }
 
 pub fn filter_out_check_moves(board: &mut Board, raw_moves: Vec<Move>) -> Vec<Move> {
@@ -248,16 +172,18 @@ pub fn filter_out_check_moves(board: &mut Board, raw_moves: Vec<Move>) -> Vec<Mo
 
 pub fn all_possible_raw_moves(board: &Board) -> Vec<Move> {
     // checkout whose turn it is from board
-    // filter out all pieces of that color from board.squares
+    // filter out all pieces of that color from the board
     // for each piece, get its raw moves
     // filter moves which cause same side to get a check
     // return vector of Move's
     let mut raw_moves: Vec<Move> = Vec::new();
-    //sort it such that queen moves are first
-    for (loc, piece) in &board.piecemap {
-        if piece.color == board.side_to_move {
-            let mut rm = get_raw_moves(&piece, &loc, &board);
-            // println!("{:?} {:?}",piece.piece_type ,rm.iter().map(|m| decode_move(&m).1).map(|n| decode_pos(&n)).collect::<Vec<_>>());
+    for piece_type in PIECE_TYPES {
+        let piece = Piece {
+            color: board.side_to_move,
+            piece_type,
+        };
+        for loc in squares(board.piece_bb(piece.color, piece_type)) {
+            let mut rm = get_raw_moves(&piece, &loc, board);
             raw_moves.append(&mut rm);
         }
     }
@@ -270,7 +196,7 @@ pub fn all_possible_valid_moves(board: &mut Board) -> Vec<Move> {
 
 pub fn find_in_raw_move_targets(
     board: &Board,
-    searchpos: &Position,
+    searchpos: &Square,
     opponent_col: &PieceColor,
 ) -> bool {
     square_search(board, searchpos, opponent_col)
@@ -280,239 +206,30 @@ pub fn find_in_raw_move_targets(
         || king_search(board, searchpos, opponent_col)
 }
 
-pub fn square_search(board: &Board, searchpos: &Position, opponent_col: &PieceColor) -> bool {
-    let (r, f) = decode_pos(searchpos);
-    let mut i = r + 1;
-    while i < 8 {
-        let p = board.get_piece(encode_pos(i as u8, f as u8));
-        if p.is_some() {
-            if p.unwrap().color == *opponent_col
-                && (p.unwrap().piece_type == PieceType::QUEEN
-                    || p.unwrap().piece_type == PieceType::ROOK)
-            {
-                return true;
-            } else {
-                break;
-            }
-        }
-        i += 1;
-    }
-    i = r - 1;
-    while i >= 0 {
-        let p = board.get_piece(encode_pos(i as u8, f as u8));
-        if p.is_some() {
-            if p.unwrap().color == *opponent_col
-                && (p.unwrap().piece_type == PieceType::QUEEN
-                    || p.unwrap().piece_type == PieceType::ROOK)
-            {
-                return true;
-            } else {
-                break;
-            }
-        }
-        i -= 1;
-    }
-    i = f + 1;
-    while i < 8 {
-        let p = board.get_piece(encode_pos(r as u8, i as u8));
-        if p.is_some() {
-            if p.unwrap().color == *opponent_col
-                && (p.unwrap().piece_type == PieceType::QUEEN
-                    || p.unwrap().piece_type == PieceType::ROOK)
-            {
-                return true;
-            } else {
-                break;
-            }
-        }
-        i += 1;
-    }
-    i = f - 1;
-    while i >= 0 {
-        let p = board.get_piece(encode_pos(r as u8, i as u8));
-        if p.is_some() {
-            if p.unwrap().color == *opponent_col
-                && (p.unwrap().piece_type == PieceType::QUEEN
-                    || p.unwrap().piece_type == PieceType::ROOK)
-            {
-                return true;
-            } else {
-                break;
-            }
-        }
-        i -= 1;
-    }
-
-    return false;
+pub fn square_search(board: &Board, searchpos: &Square, opponent_col: &PieceColor) -> bool {
+    let sliders = board.piece_bb(*opponent_col, PieceType::ROOK)
+        | board.piece_bb(*opponent_col, PieceType::QUEEN);
+    rook_attacks(*searchpos, board.occupied()) & sliders != EMPTY
 }
 
-pub fn diag_search(board: &Board, searchpos: &Position, opponent_col: &PieceColor) -> bool {
-    let (r, f) = decode_pos(searchpos);
-    let mut i = r + 1;
-    let mut j = f + 1;
-    while i < 8 && j < 8 {
-        let p = board.get_piece(encode_pos(i as u8, j as u8));
-        if p.is_some() {
-            if p.unwrap().color == *opponent_col
-                && (p.unwrap().piece_type == PieceType::QUEEN
-                    || p.unwrap().piece_type == PieceType::BISHOP)
-            {
-                return true;
-            } else {
-                break;
-            }
-        }
-        i += 1;
-        j += 1;
-    }
-    i = r - 1;
-    j = f - 1;
-    while i >= 0 && j >= 0 {
-        let p = board.get_piece(encode_pos(i as u8, j as u8));
-        if p.is_some() {
-            if p.unwrap().color == *opponent_col
-                && (p.unwrap().piece_type == PieceType::QUEEN
-                    || p.unwrap().piece_type == PieceType::BISHOP)
-            {
-                return true;
-            } else {
-                break;
-            }
-        }
-        i -= 1;
-        j -= 1;
-    }
-    i = r + 1;
-    j = f - 1;
-    while i < 8 && j >= 0 {
-        let p = board.get_piece(encode_pos(i as u8, j as u8));
-        if p.is_some() {
-            if p.unwrap().color == *opponent_col
-                && (p.unwrap().piece_type == PieceType::QUEEN
-                    || p.unwrap().piece_type == PieceType::BISHOP)
-            {
-                return true;
-            } else {
-                break;
-            }
-        }
-        i += 1;
-        j -= 1;
-    }
-    i = r - 1;
-    j = f + 1;
-    while i >= 0 && j < 8 {
-        let p = board.get_piece(encode_pos(i as u8, j as u8));
-        if p.is_some() {
-            if p.unwrap().color == *opponent_col
-                && (p.unwrap().piece_type == PieceType::QUEEN
-                    || p.unwrap().piece_type == PieceType::BISHOP)
-            {
-                return true;
-            } else {
-                break;
-            }
-        }
-        i -= 1;
-        j += 1;
-    }
-    return false;
+pub fn diag_search(board: &Board, searchpos: &Square, opponent_col: &PieceColor) -> bool {
+    let sliders = board.piece_bb(*opponent_col, PieceType::BISHOP)
+        | board.piece_bb(*opponent_col, PieceType::QUEEN);
+    bishop_attacks(*searchpos, board.occupied()) & sliders != EMPTY
 }
 
-pub fn knight_search(board: &Board, searchpos: &Position, opponent_col: &PieceColor) -> bool {
-    let (r, f) = decode_pos(searchpos);
-    let coeffs = [
-        (1, 2),
-        (2, 1),
-        (2, -1),
-        (1, -2),
-        (-1, -2),
-        (-2, -1),
-        (-2, 1),
-        (-1, 2),
-    ];
-    let mut dir: usize = 0;
-    while dir <= 7 {
-        let coeff_1 = coeffs[dir].0;
-        let coeff_2 = coeffs[dir].1;
-        let newx: i16 = r as i16 + coeff_1;
-        let newy: i16 = f as i16 + coeff_2;
-        if in_bounds!(newx, newy) {
-            let m = encode_pos(newx as u8, newy as u8);
-            let p = board.get_piece(m);
-            if p.is_some()
-                && p.unwrap().color == *opponent_col
-                && p.unwrap().piece_type == PieceType::KNIGHT
-            {
-                return true;
-            }
-        }
-        dir += 1;
-    }
-
-    return false;
+pub fn knight_search(board: &Board, searchpos: &Square, opponent_col: &PieceColor) -> bool {
+    knight_attacks(*searchpos) & board.piece_bb(*opponent_col, PieceType::KNIGHT) != EMPTY
 }
 
-pub fn king_search(board: &Board, searchpos: &Position, opponent_col: &PieceColor) -> bool {
-    let (r, f) = decode_pos(searchpos);
-    let coeffs = [
-        (1, 0),
-        (0, 1),
-        (-1, 0),
-        (0, -1),
-        (1, 1),
-        (-1, -1),
-        (-1, 1),
-        (1, -1),
-    ];
-    let mut dir: usize = 0;
-    while dir <= 7 {
-        let coeff_1 = coeffs[dir].0;
-        let coeff_2 = coeffs[dir].1;
-        let newx: i16 = r as i16 + coeff_1;
-        let newy: i16 = f as i16 + coeff_2;
-        if in_bounds!(newx, newy) {
-            let m = encode_pos(newx as u8, newy as u8);
-            let p = board.get_piece(m);
-            if p.is_some()
-                && p.unwrap().color == *opponent_col
-                && p.unwrap().piece_type == PieceType::KING
-            {
-                return true;
-            }
-        }
-        dir += 1;
-    }
-
-    return false;
+pub fn king_search(board: &Board, searchpos: &Square, opponent_col: &PieceColor) -> bool {
+    king_attacks(*searchpos) & board.piece_bb(*opponent_col, PieceType::KING) != EMPTY
 }
 
-pub fn pawn_search(board: &Board, searchpos: &Position, opponent_col: &PieceColor) -> bool {
-    let (r, f) = decode_pos(searchpos);
-    let coeff = match opponent_col {
-        super::piece::PieceColor::WHITE => 1,
-        super::piece::PieceColor::BLACK => -1,
-    };
-    let coeffs = [(1, 1), (1, -1)];
-    for cof in coeffs {
-        let coeff_1 = cof.0;
-        let coeff_2 = cof.1;
-        let newx: i16 = r as i16 + coeff_1 * coeff;
-        let newy: i16 = f as i16 + coeff_2;
-        if in_bounds!(newx, newy) {
-            let m = encode_pos(newx as u8, newy as u8);
-            let p = board.get_piece(m);
-            if p.is_some()
-                && p.unwrap().color == *opponent_col
-                && p.unwrap().piece_type == PieceType::PAWN
-            {
-                let p = board.get_piece(m);
-                if p.is_some() && p.unwrap().piece_type == PieceType::PAWN {
-                    return true;
-                }
-            }
-        }
-    }
-
-    return false;
+// an opponent pawn attacks `searchpos` exactly when a pawn of our color on
+// `searchpos` would attack the opponent pawn's square
+pub fn pawn_search(board: &Board, searchpos: &Square, opponent_col: &PieceColor) -> bool {
+    pawn_attacks(*searchpos, opponent_col.opponent_color())
+        & board.piece_bb(*opponent_col, PieceType::PAWN)
+        != EMPTY
 }
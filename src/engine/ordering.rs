@@ -0,0 +1,150 @@
+use super::{board::Board, piece::PieceType, search::MAX_PLY, Move, EN_PASSANT_CAPTURE};
+
+// order scores of the move classes, each above everything in the next one
+const HASH_MOVE_SCORE: i32 = 1 << 30;
+const CAPTURE_SCORE: i32 = 1 << 29;
+const KILLER_SCORE: i32 = 1 << 28;
+const COUNTER_MOVE_SCORE: i32 = 1 << 27;
+// history scores are halved once one of them reaches this, which keeps
+// them below COUNTER_MOVE_SCORE and lets older cutoffs fade
+const HISTORY_LIMIT: i32 = 1 << 20;
+
+// killers kept per ply
+const KILLERS: usize = 2;
+
+// piece values for MVV-LVA, only their order matters
+fn rank_of(piece_type: PieceType) -> i32 {
+    match piece_type {
+        PieceType::PAWN => 1,
+        PieceType::KNIGHT => 2,
+        PieceType::BISHOP => 3,
+        PieceType::ROOK => 4,
+        PieceType::QUEEN => 5,
+        PieceType::KING => 6,
+    }
+}
+
+/*
+What the search has learnt about which moves are worth trying first. The
+move from the transposition table comes first, then captures and
+promotions by MVV-LVA (the most valuable victim, taken by the least
+valuable attacker), then the killers, quiet moves that caused a cutoff at
+the same ply, then the counter-move to the opponent's last move, and the
+remaining quiet moves by how often they caused cutoffs anywhere in the
+tree. Used for one search, and kept across its iterations.
+ */
+pub struct MoveOrdering {
+    killers: Vec<[Option<Move>; KILLERS]>,
+    // indexed by the from and to squares of the move being answered
+    counter_moves: Vec<[Option<Move>; 64]>,
+    // indexed by side, from and to square
+    history: Vec<[[i32; 64]; 64]>,
+    // beta cutoffs, and those caused by the first move searched
+    pub cutoffs: u64,
+    pub first_move_cutoffs: u64,
+}
+
+impl MoveOrdering {
+    pub fn new() -> MoveOrdering {
+        MoveOrdering {
+            killers: vec![[None; KILLERS]; MAX_PLY as usize + 1],
+            counter_moves: vec![[None; 64]; 64],
+            history: vec![[[0; 64]; 64]; 2],
+            cutoffs: 0,
+            first_move_cutoffs: 0,
+        }
+    }
+    // `prev` is the move that led to the position, if any
+    pub fn order(
+        &self,
+        board: &Board,
+        moves: &mut [Move],
+        hash_move: Option<Move>,
+        ply: u8,
+        prev: Option<Move>,
+    ) {
+        let counter = prev.and_then(|p| self.counter_moves[p.from().index()][p.to().index()]);
+        let side = board.side_to_move as usize;
+        moves.sort_by_cached_key(|m| {
+            let score = if Some(*m) == hash_move {
+                HASH_MOVE_SCORE
+            } else if is_tactical(*m) {
+                CAPTURE_SCORE + mvv_lva(board, *m)
+            } else if let Some(k) = self.killers[ply as usize]
+                .iter()
+                .position(|k| *k == Some(*m))
+            {
+                KILLER_SCORE - k as i32
+            } else if counter == Some(*m) {
+                COUNTER_MOVE_SCORE
+            } else {
+                self.history[side][m.from().index()][m.to().index()]
+            };
+            -score
+        });
+    }
+    /*
+    called when `m`, the `index`th move searched, refuted the position at
+    `ply`. captures are already ordered well, so only quiet moves are
+    remembered
+     */
+    pub fn record_cutoff(
+        &mut self,
+        board: &Board,
+        m: Move,
+        index: usize,
+        depth: u8,
+        ply: u8,
+        prev: Option<Move>,
+    ) {
+        self.cutoffs += 1;
+        if index == 0 {
+            self.first_move_cutoffs += 1;
+        }
+        if is_tactical(m) {
+            return;
+        }
+        let killers = &mut self.killers[ply as usize];
+        if killers[0] != Some(m) {
+            killers[1] = killers[0];
+            killers[0] = Some(m);
+        }
+        if let Some(p) = prev {
+            self.counter_moves[p.from().index()][p.to().index()] = Some(m);
+        }
+        let history = &mut self.history[board.side_to_move as usize];
+        let entry = &mut history[m.from().index()][m.to().index()];
+        *entry += depth as i32 * depth as i32;
+        if *entry >= HISTORY_LIMIT {
+            for scores in history.iter_mut() {
+                for score in scores.iter_mut() {
+                    *score /= 2;
+                }
+            }
+        }
+    }
+}
+
+impl Default for MoveOrdering {
+    fn default() -> Self {
+        MoveOrdering::new()
+    }
+}
+
+fn is_tactical(m: Move) -> bool {
+    m.is_capture() || m.promotion_piece().is_some()
+}
+
+// higher for better captures, a promotion counts the new piece as won
+pub fn mvv_lva(board: &Board, m: Move) -> i32 {
+    let victim = if m.flags() == EN_PASSANT_CAPTURE {
+        rank_of(PieceType::PAWN)
+    } else {
+        board.get_piece(m.to()).map_or(0, |p| rank_of(p.piece_type))
+    };
+    let promotion = m.promotion_piece().map_or(0, rank_of);
+    let attacker = board
+        .get_piece(m.from())
+        .map_or(0, |p| rank_of(p.piece_type));
+    (victim + promotion) * 8 - attacker
+}
@@ -0,0 +1,120 @@
+use super::{board::Board, moves::all_possible_valid_moves, Move};
+
+impl Board {
+    // number of leaf nodes of the legal move tree `depth` plies deep
+    pub fn perft(&mut self, depth: u8) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+        let moves = all_possible_valid_moves(self);
+        if depth == 1 {
+            return moves.len() as u64;
+        }
+        let mut nodes = 0;
+        for m in moves {
+            let ctx = self.make_move(m);
+            nodes += self.perft(depth - 1);
+            self.unmake_move(ctx);
+        }
+        nodes
+    }
+    // perft split by root move, sorted by move string so it can be diffed
+    // against other engines' output
+    pub fn divide(&mut self, depth: u8) -> Vec<(Move, u64)> {
+        let mut result = Vec::new();
+        if depth == 0 {
+            return result;
+        }
+        for m in all_possible_valid_moves(self) {
+            let ctx = self.make_move(m);
+            result.push((m, self.perft(depth - 1)));
+            self.unmake_move(ctx);
+        }
+        result.sort_by_key(|(m, _)| m.to_string());
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::engine::board::{create_board, START_FEN};
+
+    // the standard perft positions from the chess programming wiki, with their
+    // published node counts for depth 1, 2, ...
+    const REFERENCE_POSITIONS: [(&str, &str, &[u64]); 7] = [
+        (
+            "start position",
+            START_FEN,
+            &[20, 400, 8902, 197281, 4865609],
+        ),
+        (
+            "kiwipete",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            &[48, 2039, 97862, 4085603],
+        ),
+        (
+            "position 3",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            &[14, 191, 2812, 43238, 674624, 11030083],
+        ),
+        (
+            "position 4",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            &[6, 264, 9467, 422333, 15833292],
+        ),
+        (
+            "position 4 mirrored",
+            "r2q1rk1/pP1p2pp/Q4n2/bbp1p3/Np6/1B3NBn/pPPP1PPP/R3K2R b KQ - 0 1",
+            &[6, 264, 9467, 422333, 15833292],
+        ),
+        (
+            "position 5",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+            &[44, 1486, 62379, 2103487],
+        ),
+        (
+            "position 6",
+            "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+            &[46, 2079, 89890, 3894594],
+        ),
+    ];
+
+    // checks every published count up to `max_nodes` leaf nodes
+    fn check_reference_positions(max_nodes: u64) {
+        for (name, fen, counts) in REFERENCE_POSITIONS {
+            let mut board = create_board(fen).unwrap();
+            for (i, expected) in counts.iter().enumerate() {
+                if *expected > max_nodes {
+                    break;
+                }
+                let depth = i as u8 + 1;
+                assert_eq!(board.perft(depth), *expected, "{} depth {}", name, depth);
+            }
+        }
+    }
+
+    #[test]
+    fn reference_positions() {
+        check_reference_positions(100_000);
+    }
+
+    // takes minutes without optimizations, run with
+    // `cargo test --release -- --ignored`
+    #[test]
+    #[ignore]
+    fn reference_positions_deep() {
+        check_reference_positions(u64::MAX);
+    }
+
+    #[test]
+    fn divide_adds_up_to_perft() {
+        let mut board = create_board(START_FEN).unwrap();
+        let split = board.divide(3);
+        assert_eq!(split.len(), 20);
+        assert!(split
+            .windows(2)
+            .all(|w| w[0].0.to_string() < w[1].0.to_string()));
+        assert_eq!(split.iter().map(|(_, n)| n).sum::<u64>(), 8902);
+        assert_eq!(board.to_fen(), START_FEN);
+    }
+}
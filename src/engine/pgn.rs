@@ -0,0 +1,534 @@
+use std::fmt;
+
+use super::{
+    board::{create_board, FenError, START_FEN},
+    game::Game,
+    piece::PieceColor,
+    san::SanError,
+    status::GameStatus,
+};
+
+// the tags every exported game carries, in this order, with the values
+// used when they are unknown
+pub const SEVEN_TAG_ROSTER: [(&str, &str); 7] = [
+    ("Event", "?"),
+    ("Site", "?"),
+    ("Date", "????.??.??"),
+    ("Round", "?"),
+    ("White", "?"),
+    ("Black", "?"),
+    ("Result", "*"),
+];
+
+const RESULTS: [&str; 4] = ["1-0", "0-1", "1/2-1/2", "*"];
+
+// movetext lines are kept to this many characters
+const LINE_WIDTH: usize = 80;
+
+// `line` is the 1-based line of the PGN text where the problem starts
+#[derive(Debug, Clone, PartialEq)]
+pub enum PgnError {
+    BadTag {
+        line: usize,
+    },
+    UnterminatedComment {
+        line: usize,
+    },
+    UnterminatedVariation {
+        line: usize,
+    },
+    BadFen {
+        line: usize,
+        error: FenError,
+    },
+    IllegalMove {
+        line: usize,
+        // the move number and side as they would be written in the PGN
+        move_number: u16,
+        color: PieceColor,
+        san: String,
+        error: SanError,
+    },
+}
+
+impl fmt::Display for PgnError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PgnError::BadTag { line } => write!(f, "malformed tag pair on line {}", line),
+            PgnError::UnterminatedComment { line } => {
+                write!(f, "comment starting on line {} is never closed", line)
+            }
+            PgnError::UnterminatedVariation { line } => {
+                write!(f, "variation starting on line {} is never closed", line)
+            }
+            PgnError::BadFen { line, error } => write!(f, "{} (FEN tag on line {})", error, line),
+            PgnError::IllegalMove {
+                line,
+                move_number,
+                color,
+                san,
+                error,
+            } => {
+                let dots = match color {
+                    PieceColor::WHITE => ".",
+                    PieceColor::BLACK => "...",
+                };
+                write!(
+                    f,
+                    "{}{} {} on line {}: {}",
+                    move_number, dots, san, line, error
+                )
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Tag(String, String),
+    Move(String),
+    Result(String),
+}
+
+/*
+splits PGN text into tag pairs, moves and results, each with the line it
+starts on. comments, variations, NAGs, move numbers and escaped lines are
+dropped here since only the main line is replayed
+ */
+fn tokenize(text: &str) -> Result<Vec<(usize, Token)>, PgnError> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let mut line = 1;
+    let mut i = 0;
+    // skips to the end of the current line, leaving `i` on the newline
+    let skip_line = |i: &mut usize| {
+        while *i < chars.len() && chars[*i] != '\n' {
+            *i += 1;
+        }
+    };
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '\n' => {
+                line += 1;
+                i += 1;
+            }
+            _ if c.is_whitespace() => i += 1,
+            // a % in the first column escapes the whole line
+            '%' if i == 0 || chars[i - 1] == '\n' => skip_line(&mut i),
+            ';' => skip_line(&mut i),
+            '{' => {
+                let start = line;
+                i += 1;
+                while i < chars.len() && chars[i] != '}' {
+                    if chars[i] == '\n' {
+                        line += 1;
+                    }
+                    i += 1;
+                }
+                if i == chars.len() {
+                    return Err(PgnError::UnterminatedComment { line: start });
+                }
+                i += 1;
+            }
+            '(' => {
+                // variations nest, and may hold comments with parentheses
+                let start = line;
+                let mut depth = 0;
+                let mut in_comment = false;
+                while i < chars.len() {
+                    match chars[i] {
+                        '\n' => line += 1,
+                        '{' => in_comment = true,
+                        '}' => in_comment = false,
+                        '(' if !in_comment => depth += 1,
+                        ')' if !in_comment => depth -= 1,
+                        _ => {}
+                    }
+                    i += 1;
+                    if depth == 0 {
+                        break;
+                    }
+                }
+                if depth != 0 {
+                    return Err(PgnError::UnterminatedVariation { line: start });
+                }
+            }
+            '[' => {
+                // the value may hold brackets, so the closing one is only
+                // looked for outside the quotes
+                let mut end = i + 1;
+                let mut in_value = false;
+                while end < chars.len() && chars[end] != '\n' {
+                    match chars[end] {
+                        '\\' if in_value => end += 1,
+                        '"' => in_value = !in_value,
+                        ']' if !in_value => break,
+                        _ => {}
+                    }
+                    end += 1;
+                }
+                if end >= chars.len() || chars[end] != ']' {
+                    return Err(PgnError::BadTag { line });
+                }
+                let inner: String = chars[i + 1..end].iter().collect();
+                let (name, value) = parse_tag(&inner).ok_or(PgnError::BadTag { line })?;
+                tokens.push((line, Token::Tag(name, value)));
+                i = end + 1;
+            }
+            _ => {
+                let start = i;
+                while i < chars.len()
+                    && !chars[i].is_whitespace()
+                    && !matches!(chars[i], '{' | '}' | '(' | ')' | '[' | ']' | ';')
+                {
+                    i += 1;
+                }
+                if start == i {
+                    // a stray closing bracket
+                    i += 1;
+                    continue;
+                }
+                let word: String = chars[start..i].iter().collect();
+                if RESULTS.contains(&word.as_str()) {
+                    tokens.push((line, Token::Result(word)));
+                    continue;
+                }
+                if word.starts_with('$') || word.chars().all(|c| c.is_ascii_digit()) {
+                    continue;
+                }
+                // move numbers may be glued to the move, as in `12.e4`. `0-0`
+                // starts with a digit too, but no dot follows it
+                let digits = word.find(|c: char| !c.is_ascii_digit()).unwrap_or(0);
+                let word = if digits > 0 && word[digits..].starts_with('.') {
+                    word[digits..].trim_start_matches('.')
+                } else {
+                    &word
+                };
+                if word.is_empty() {
+                    continue;
+                }
+                // annotations written apart from their move
+                if word == "e.p." || word.chars().all(|c| matches!(c, '!' | '?')) {
+                    continue;
+                }
+                tokens.push((line, Token::Move(word.to_string())));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+// `inner` is what is between the brackets of a tag pair: `Name "value"`
+fn parse_tag(inner: &str) -> Option<(String, String)> {
+    let inner = inner.trim();
+    let name_end = inner.find(|c: char| c.is_whitespace())?;
+    let name = &inner[..name_end];
+    if !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return None;
+    }
+    let quoted = inner[name_end..].trim();
+    let quoted = quoted.strip_prefix('"')?.strip_suffix('"')?;
+    let mut value = String::new();
+    let mut escaped = false;
+    for c in quoted.chars() {
+        match c {
+            '\\' if !escaped => escaped = true,
+            _ => {
+                value.push(c);
+                escaped = false;
+            }
+        }
+    }
+    Some((name.to_string(), value))
+}
+
+fn escape_tag_value(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+// a game with its PGN tags and result
+pub struct PgnGame {
+    pub tags: Vec<(String, String)>,
+    pub game: Game,
+    // one of `1-0`, `0-1`, `1/2-1/2` or `*`
+    pub result: String,
+}
+
+impl PgnGame {
+    // the result is taken from the game's status, `*` while it is ongoing
+    pub fn new(mut game: Game) -> PgnGame {
+        let result = match game.status() {
+            GameStatus::CHECKMATE {
+                winner: PieceColor::WHITE,
+            } => "1-0",
+            GameStatus::CHECKMATE {
+                winner: PieceColor::BLACK,
+            } => "0-1",
+            status if status.is_game_over() => "1/2-1/2",
+            _ => "*",
+        };
+        PgnGame {
+            tags: Vec::new(),
+            game,
+            result: result.to_string(),
+        }
+    }
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        match self.tags.iter_mut().find(|(n, _)| n == name) {
+            Some(tag) => tag.1 = value.to_string(),
+            None => self.tags.push((name.to_string(), value.to_string())),
+        }
+    }
+    /*
+    the game as PGN: the seven tag roster first (the Result tag always
+    agrees with `result`), then the other tags, SetUp and FEN when the
+    game doesn't start from the initial position, and the moves in SAN
+     */
+    pub fn to_pgn(&self) -> String {
+        let mut tags: Vec<(String, String)> = SEVEN_TAG_ROSTER
+            .iter()
+            .map(|(name, default)| {
+                let value = match *name {
+                    "Result" => self.result.as_str(),
+                    _ => self.tag(name).unwrap_or(default),
+                };
+                (name.to_string(), value.to_string())
+            })
+            .collect();
+        for (name, value) in self.tags.iter() {
+            if !SEVEN_TAG_ROSTER.iter().any(|(n, _)| n == name) {
+                tags.push((name.clone(), value.clone()));
+            }
+        }
+        let start_fen = self.game.start_fen();
+        if start_fen != START_FEN && self.tag("FEN").is_none() {
+            tags.push(("SetUp".to_string(), "1".to_string()));
+            tags.push(("FEN".to_string(), start_fen.to_string()));
+        }
+        let mut pgn = String::new();
+        for (name, value) in tags {
+            pgn.push_str(&format!("[{} \"{}\"]\n", name, escape_tag_value(&value)));
+        }
+        pgn.push('\n');
+
+        let mut board = create_board(start_fen).expect("game start position must be valid");
+        let mut words = Vec::new();
+        for (i, m) in self.game.moves().iter().enumerate() {
+            // move numbers are kept on the same line as their move
+            let san = board.san(*m);
+            words.push(match board.side_to_move {
+                PieceColor::WHITE => format!("{}. {}", board.fullmove_number, san),
+                PieceColor::BLACK if i == 0 => format!("{}... {}", board.fullmove_number, san),
+                PieceColor::BLACK => san,
+            });
+            board.make_move(*m);
+        }
+        words.push(self.result.clone());
+        let mut line_len = 0;
+        for word in words {
+            if line_len > 0 && line_len + 1 + word.len() > LINE_WIDTH {
+                pgn.push('\n');
+                line_len = 0;
+            } else if line_len > 0 {
+                pgn.push(' ');
+                line_len += 1;
+            }
+            line_len += word.len();
+            pgn.push_str(&word);
+        }
+        pgn.push('\n');
+        pgn
+    }
+}
+
+// closes off a game whose moves or tags have been read
+fn finish_game(
+    tags: Vec<(String, String)>,
+    game: Option<Game>,
+    result: Option<String>,
+    line: usize,
+) -> Result<PgnGame, PgnError> {
+    let game = match game {
+        Some(game) => game,
+        None => start_game(&tags, line)?,
+    };
+    let result = result.or_else(|| {
+        tags.iter()
+            .find(|(n, v)| n == "Result" && RESULTS.contains(&v.as_str()))
+            .map(|(_, v)| v.clone())
+    });
+    // without one the result is taken from the game, so a mate counts
+    let mut pgn = PgnGame::new(game);
+    for (name, value) in tags.iter() {
+        pgn.set_tag(name, value);
+    }
+    if let Some(result) = result {
+        pgn.result = result;
+    }
+    Ok(pgn)
+}
+
+// the game the tags describe, from the FEN tag if there is one
+fn start_game(tags: &[(String, String)], line: usize) -> Result<Game, PgnError> {
+    let fen = tags
+        .iter()
+        .find(|(n, _)| n == "FEN")
+        .map_or(START_FEN, |(_, v)| v.as_str());
+    Game::from_fen(fen).map_err(|error| PgnError::BadFen { line, error })
+}
+
+/*
+reads every game in `text`, replaying the main line of each through the
+move generator. a game ends at its result, or where the next game's tags
+begin if the result is missing
+ */
+pub fn read_pgn(text: &str) -> Result<Vec<PgnGame>, PgnError> {
+    let mut games = Vec::new();
+    let mut tags = Vec::new();
+    let mut game: Option<Game> = None;
+    let mut last_line = 1;
+    for (line, token) in tokenize(text)? {
+        last_line = line;
+        match token {
+            Token::Tag(name, value) => {
+                if game.is_some() {
+                    games.push(finish_game(
+                        std::mem::take(&mut tags),
+                        game.take(),
+                        None,
+                        line,
+                    )?);
+                }
+                tags.push((name, value));
+            }
+            Token::Move(san) => {
+                if game.is_none() {
+                    game = Some(start_game(&tags, line)?);
+                }
+                let g = game.as_mut().unwrap();
+                let (move_number, color) = (g.board().fullmove_number, g.board().side_to_move);
+                if let Err(error) = g.push_san(&san) {
+                    return Err(PgnError::IllegalMove {
+                        line,
+                        move_number,
+                        color,
+                        san,
+                        error,
+                    });
+                }
+            }
+            Token::Result(result) => {
+                games.push(finish_game(
+                    std::mem::take(&mut tags),
+                    game.take(),
+                    Some(result),
+                    line,
+                )?);
+            }
+        }
+    }
+    if game.is_some() || !tags.is_empty() {
+        games.push(finish_game(tags, game, None, last_line)?);
+    }
+    Ok(games)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tag_values_may_hold_brackets_and_quotes() {
+        let games =
+            read_pgn("[Event \"Test [x]\"]\n[Site \"a \\\"b\\\" ]\"]\n\n1. e4 *\n").unwrap();
+        assert_eq!(games.len(), 1);
+        assert_eq!(games[0].tag("Event"), Some("Test [x]"));
+        assert_eq!(games[0].tag("Site"), Some("a \"b\" ]"));
+        assert_eq!(
+            read_pgn("[Event \"Test]\n1. e4 *\n").err(),
+            Some(PgnError::BadTag { line: 1 })
+        );
+    }
+
+    #[test]
+    fn reads_the_main_line_of_an_annotated_game() {
+        let text = "[Event \"Annotated\"]
+% an escaped line [is not a tag
+[White \"A\"]
+
+1. e4 {best by test} e5 ; the rest of the line is a comment ]
+2.Nf3 $1 (2. f4 exf4 {a comment with (parentheses)} (2... d5) 3. Nf3)
+2... Nc6 3.Bb5!? a6 1-0
+";
+        let games = read_pgn(text).unwrap();
+        assert_eq!(games.len(), 1);
+        let game = &games[0];
+        assert_eq!(game.tags.len(), 2);
+        assert_eq!(game.tag("White"), Some("A"));
+        assert_eq!(game.result, "1-0");
+        let moves: Vec<String> = game.game.moves().iter().map(|m| m.to_string()).collect();
+        assert_eq!(moves, ["e2e4", "e7e5", "g1f3", "b8c6", "f1b5", "a7a6"]);
+    }
+
+    #[test]
+    fn reports_where_an_illegal_move_is() {
+        let error = read_pgn("[Event \"x\"]\n\n1. e4 e5\n2. Ke3 Nc6 *\n")
+            .err()
+            .unwrap();
+        assert_eq!(
+            error,
+            PgnError::IllegalMove {
+                line: 4,
+                move_number: 2,
+                color: PieceColor::WHITE,
+                san: "Ke3".to_string(),
+                error: SanError::Illegal("Ke3".to_string()),
+            }
+        );
+        assert_eq!(
+            error.to_string(),
+            "2. Ke3 on line 4: 'Ke3' is not a legal move"
+        );
+    }
+
+    #[test]
+    fn missing_result_is_taken_from_the_game() {
+        let games = read_pgn("1. f3 e5 2. g4 Qh4#\n").unwrap();
+        assert_eq!(games[0].result, "0-1");
+        let games = read_pgn("[Result \"1/2-1/2\"]\n\n1. e4 e5\n").unwrap();
+        assert_eq!(games[0].result, "1/2-1/2");
+    }
+
+    #[test]
+    fn written_games_read_back_the_same() {
+        let mut game = Game::from_fen("r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 30").unwrap();
+        for san in ["O-O", "O-O-O", "Ra1+", "Kb2", "Rxd1", "Rxd1", "Rf2+", "Kc3"] {
+            game.push_san(san).unwrap();
+        }
+        let mut pgn = PgnGame::new(game);
+        pgn.set_tag("Event", "Test [x]");
+        pgn.set_tag("White", "\"Magnus\"");
+        pgn.set_tag("Event", "Test [y]");
+        pgn.set_tag("Annotator", "none");
+        assert_eq!(pgn.result, "*");
+
+        let text = pgn.to_pgn();
+        let games = read_pgn(&text).unwrap();
+        assert_eq!(games.len(), 1);
+        let read = &games[0];
+        assert_eq!(read.tag("Event"), Some("Test [y]"));
+        assert_eq!(read.tag("White"), Some("\"Magnus\""));
+        assert_eq!(read.tag("Annotator"), Some("none"));
+        assert_eq!(read.game.start_fen(), pgn.game.start_fen());
+        assert_eq!(read.game.moves(), pgn.game.moves());
+        assert_eq!(read.result, pgn.result);
+        assert_eq!(read.to_pgn(), text);
+    }
+}
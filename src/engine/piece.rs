@@ -1,10 +1,11 @@
 use std::fmt;
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct Piece {
     pub color: PieceColor,
     pub piece_type: PieceType,
 }
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub enum PieceType {
     PAWN,
@@ -14,6 +15,15 @@ pub enum PieceType {
     QUEEN,
     KING,
 }
+pub const PIECE_TYPES: [PieceType; 6] = [
+    PieceType::PAWN,
+    PieceType::KNIGHT,
+    PieceType::BISHOP,
+    PieceType::ROOK,
+    PieceType::QUEEN,
+    PieceType::KING,
+];
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub enum PieceColor {
     WHITE,
@@ -61,16 +71,13 @@ impl fmt::Display for Piece {
 }
 
 impl Piece {
-    // pub fn new(color: PieceColor, piece_type: PieceType) -> Piece {
-    //     Piece {
-    //         color: color,
-    //         piece_type: piece_type,
-    //     }
-    // }
+    pub fn new(color: PieceColor, piece_type: PieceType) -> Piece {
+        Piece { color, piece_type }
+    }
     pub fn get_color(&self) -> PieceColor {
         self.color
     }
-    // pub fn moves(&self, base: super::board::Position) -> Vec<super::board::Position> {
+    // pub fn moves(&self, base: super::square::Square) -> Vec<super::square::Square> {
     //     match self.piece_type {
     //         PieceType::PAWN => pawn_moves(base, self.color),
     //         PieceType::KNIGHT => knight_moves(base),
@@ -0,0 +1,305 @@
+use std::fmt;
+
+use super::{
+    board::Board,
+    moves::{all_possible_valid_moves, match_valid_move},
+    piece::PieceType,
+    square::Square,
+    Move, KING_CASTLE, QUEEN_CASTLE,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SanError {
+    // the text isn't shaped like a move
+    Malformed(String),
+    // no legal move matches
+    Illegal(String),
+    // more than one legal move matches
+    Ambiguous(String),
+}
+
+impl fmt::Display for SanError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SanError::Malformed(san) => write!(f, "'{}' is not a move", san),
+            SanError::Illegal(san) => write!(f, "'{}' is not a legal move", san),
+            SanError::Ambiguous(san) => write!(f, "'{}' matches more than one legal move", san),
+        }
+    }
+}
+
+fn piece_letter(piece_type: PieceType) -> &'static str {
+    match piece_type {
+        PieceType::PAWN => "",
+        PieceType::KNIGHT => "N",
+        PieceType::BISHOP => "B",
+        PieceType::ROOK => "R",
+        PieceType::QUEEN => "Q",
+        PieceType::KING => "K",
+    }
+}
+
+fn piece_from_letter(c: char) -> Option<PieceType> {
+    match c.to_ascii_uppercase() {
+        'N' => Some(PieceType::KNIGHT),
+        'B' => Some(PieceType::BISHOP),
+        'R' => Some(PieceType::ROOK),
+        'Q' => Some(PieceType::QUEEN),
+        'K' => Some(PieceType::KING),
+        _ => None,
+    }
+}
+
+// a SAN move taken apart, before it is matched against the legal moves
+struct SanParts {
+    piece_type: PieceType,
+    from_file: Option<u8>,
+    from_rank: Option<u8>,
+    to: Square,
+    promotion: Option<PieceType>,
+}
+
+/*
+splits `text` into its parts. castling and the annotations around the move
+are handled by the caller, what is left looks like `Nbd7`, `exd6`, `e8=Q`
+or `Ng1-f3`
+ */
+fn split_san(text: &str) -> Option<SanParts> {
+    let mut chars: Vec<char> = text
+        .chars()
+        .filter(|c| !matches!(c, 'x' | ':' | '-'))
+        .collect();
+    let mut promotion = None;
+    if chars.len() > 2 {
+        let last = chars[chars.len() - 1];
+        let before = chars[chars.len() - 2];
+        if before == '=' || before.is_ascii_digit() {
+            if let Some(piece_type) = piece_from_letter(last).filter(|t| *t != PieceType::KING) {
+                promotion = Some(piece_type);
+                chars.pop();
+                if before == '=' {
+                    chars.pop();
+                }
+            }
+        }
+    }
+    // a capital B is always a bishop, a lowercase one is the b-file
+    let piece_type = match chars.first() {
+        Some(c) if c.is_ascii_uppercase() => piece_from_letter(*c)?,
+        _ => PieceType::PAWN,
+    };
+    let rest = if piece_type == PieceType::PAWN {
+        &chars[..]
+    } else {
+        &chars[1..]
+    };
+    if rest.len() < 2 || rest.len() > 4 {
+        return None;
+    }
+    let (from, to) = rest.split_at(rest.len() - 2);
+    let to = to.iter().collect::<String>().parse::<Square>().ok()?;
+    let (mut from_file, mut from_rank) = (None, None);
+    for c in from {
+        match c {
+            'a'..='h' if from_file.is_none() && from_rank.is_none() => {
+                from_file = Some(*c as u8 - b'a')
+            }
+            '1'..='8' if from_rank.is_none() => from_rank = Some(*c as u8 - b'1'),
+            _ => return None,
+        }
+    }
+    Some(SanParts {
+        piece_type,
+        from_file,
+        from_rank,
+        to,
+        promotion,
+    })
+}
+
+impl Board {
+    // the move in standard algebraic notation, e.g. `Nbd7`, `exd6`,
+    // `e8=Q+` or `O-O-O#`. `m` must be legal in this position
+    pub fn san(&mut self, m: Move) -> String {
+        let legal_moves = all_possible_valid_moves(self);
+        let mut san = match m.flags() {
+            KING_CASTLE => "O-O".to_string(),
+            QUEEN_CASTLE => "O-O-O".to_string(),
+            _ => {
+                let piece_type = self
+                    .get_piece(m.from())
+                    .expect("move must start on a piece")
+                    .piece_type;
+                let mut san = piece_letter(piece_type).to_string();
+                if piece_type == PieceType::PAWN {
+                    if m.is_capture() {
+                        san.push((b'a' + m.from().file()) as char);
+                    }
+                } else {
+                    // other pieces of the same type that can reach the same square
+                    let rivals: Vec<Square> = legal_moves
+                        .iter()
+                        .filter(|o| o.to() == m.to() && o.from() != m.from())
+                        .filter(|o| {
+                            self.get_piece(o.from()).map(|p| p.piece_type) == Some(piece_type)
+                        })
+                        .map(|o| o.from())
+                        .collect();
+                    if !rivals.is_empty() {
+                        let from = m.from();
+                        if rivals.iter().all(|r| r.file() != from.file()) {
+                            san.push((b'a' + from.file()) as char);
+                        } else if rivals.iter().all(|r| r.rank() != from.rank()) {
+                            san.push((b'1' + from.rank()) as char);
+                        } else {
+                            san.push_str(&from.to_string());
+                        }
+                    }
+                }
+                if m.is_capture() {
+                    san.push('x');
+                }
+                san.push_str(&m.to().to_string());
+                if let Some(promotion) = m.promotion_piece() {
+                    san.push('=');
+                    san.push_str(piece_letter(promotion));
+                }
+                san
+            }
+        };
+        let ctx = self.make_move(m);
+        let side = self.side_to_move;
+        if self.has_check(&side) {
+            if all_possible_valid_moves(self).is_empty() {
+                san.push('#');
+            } else {
+                san.push('+');
+            }
+        }
+        self.unmake_move(ctx);
+        san
+    }
+    /*
+    finds the legal move written as `text`. besides strict SAN this accepts
+    a missing or extra capture sign, `0-0` for castling, promotions without
+    `=`, long algebraic like `Ng1-f3`, UCI moves, and trailing check signs,
+    annotations like `!?` and `e.p.`, none of which have to be correct
+     */
+    pub fn parse_san(&mut self, text: &str) -> Result<Move, SanError> {
+        let malformed = || SanError::Malformed(text.to_string());
+        let mut san = text.trim();
+        if let Some(stripped) = san.strip_suffix("e.p.") {
+            san = stripped.trim_end();
+        }
+        let san = san.trim_end_matches(['+', '#', '!', '?']);
+        if san.is_empty() {
+            return Err(malformed());
+        }
+        let legal_moves = all_possible_valid_moves(self);
+        let castle = match san {
+            "O-O" | "0-0" => Some(KING_CASTLE),
+            "O-O-O" | "0-0-0" => Some(QUEEN_CASTLE),
+            _ => None,
+        };
+        if let Some(flags) = castle {
+            return legal_moves
+                .into_iter()
+                .find(|m| m.flags() == flags)
+                .ok_or_else(|| SanError::Illegal(text.to_string()));
+        }
+        if let Ok(m) = san.parse::<Move>() {
+            return match_valid_move(self, &m).ok_or_else(|| SanError::Illegal(text.to_string()));
+        }
+        let parts = split_san(san).ok_or_else(malformed)?;
+        let matching: Vec<Move> = legal_moves
+            .into_iter()
+            .filter(|m| {
+                m.to() == parts.to
+                    && m.promotion_piece() == parts.promotion
+                    && parts.from_file.is_none_or(|f| m.from().file() == f)
+                    && parts.from_rank.is_none_or(|r| m.from().rank() == r)
+                    && self.get_piece(m.from()).map(|p| p.piece_type) == Some(parts.piece_type)
+            })
+            .collect();
+        match matching[..] {
+            [m] => Ok(m),
+            [] => Err(SanError::Illegal(text.to_string())),
+            _ => Err(SanError::Ambiguous(text.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::board::create_board;
+
+    // (position, move in UCI notation, its SAN)
+    const SAN_CASES: [(&str, &str, &str); 12] = [
+        (
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "g1f3",
+            "Nf3",
+        ),
+        ("1n2k3/8/5n2/8/8/8/8/4K3 b - - 0 1", "b8d7", "Nbd7"),
+        ("4k3/8/8/6N1/8/8/8/4K1N1 w - - 0 1", "g1f3", "N1f3"),
+        ("4k3/8/8/R7/8/8/8/R3K3 w - - 0 1", "a5a3", "R5a3"),
+        ("4k3/8/8/8/8/Q7/8/Q1Q1K3 w - - 0 1", "a1b2", "Qa1b2"),
+        ("4k3/8/8/8/8/Q7/8/Q1Q1K3 w - - 0 1", "c1b2", "Qcb2"),
+        // the knight on c3 is pinned, so g1 needs no disambiguation
+        ("4k3/8/8/8/1b6/2N5/8/4K1N1 w - - 0 1", "g1e2", "Ne2"),
+        ("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6", "exd6"),
+        ("k7/4P3/8/8/8/8/8/4K3 w - - 0 1", "e7e8q", "e8=Q+"),
+        ("3rk3/4P3/8/8/8/8/8/4K3 w - - 0 1", "e7d8n", "exd8=N"),
+        ("r3k3/8/8/8/8/8/8/4K3 b q - 0 1", "e8c8", "O-O-O"),
+        ("k7/8/1K6/8/8/8/8/7R w - - 0 1", "h1h8", "Rh8#"),
+    ];
+
+    // (position, text to parse, the move it must give in UCI notation, or
+    // None when it has to be rejected)
+    const SAN_PARSE_CASES: [(&str, &str, Option<&str>); 12] = [
+        ("1n2k3/8/5n2/8/8/8/8/4K3 b - - 0 1", "Nd7", None),
+        ("1n2k3/8/5n2/8/8/8/8/4K3 b - - 0 1", "Nfd7!?", Some("f6d7")),
+        (
+            "4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1",
+            "exd6 e.p.",
+            Some("e5d6"),
+        ),
+        ("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "ed6", Some("e5d6")),
+        ("k7/4P3/8/8/8/8/8/4K3 w - - 0 1", "e8Q", Some("e7e8q")),
+        ("k7/4P3/8/8/8/8/8/4K3 w - - 0 1", "e8", None),
+        ("r3k3/8/8/8/8/8/8/4K3 b q - 0 1", "0-0-0", Some("e8c8")),
+        ("r3k3/8/8/8/8/8/8/4K3 b q - 0 1", "O-O", None),
+        (
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "Ng1-f3",
+            Some("g1f3"),
+        ),
+        (
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "e2e4",
+            Some("e2e4"),
+        ),
+        ("4k3/8/8/8/1b6/2N5/8/4K1N1 w - - 0 1", "Nce2", None),
+        ("4k3/8/8/8/1b6/2N5/8/4K1N1 w - - 0 1", "Zz9", None),
+    ];
+
+    #[test]
+    fn formats_and_parses_back() {
+        for (fen, uci, expected) in SAN_CASES {
+            let mut board = create_board(fen).unwrap();
+            let m = match_valid_move(&mut board, &uci.parse().unwrap()).unwrap();
+            assert_eq!(board.san(m), expected, "{} in {}", uci, fen);
+            assert_eq!(board.parse_san(expected), Ok(m), "{} in {}", expected, fen);
+        }
+    }
+
+    #[test]
+    fn parses_loose_notation() {
+        for (fen, text, expected) in SAN_PARSE_CASES {
+            let mut board = create_board(fen).unwrap();
+            let parsed = board.parse_san(text).map(|m| m.to_string());
+            assert_eq!(parsed.as_deref().ok(), expected, "{} in {}", text, fen);
+        }
+    }
+}
@@ -0,0 +1,766 @@
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use super::{
+    board::Board,
+    moves::all_possible_valid_moves,
+    ordering::{mvv_lva, MoveOrdering},
+    piece::{Piece, PieceColor, PieceType},
+    tt::{score_from_tt, score_to_tt, Bound, TranspositionTable},
+    weights::get_piece_weight,
+    Move, EN_PASSANT_CAPTURE,
+};
+
+// search scores, in centipawns
+pub type Score = i32;
+
+// score of mating at the root. a mate found `ply` half-moves from the root
+// scores MATE_SCORE - ply, so faster mates score higher. the range within
+// MAX_PLY of it is kept for mates, evaluations stay below MAX_EVAL
+pub const MATE_SCORE: Score = 30_000;
+pub const MAX_PLY: u8 = u8::MAX;
+pub const MAX_EVAL: Score = MATE_SCORE - MAX_PLY as Score - 1;
+// above every score, the bounds of a full window search
+pub const INFINITY: Score = MATE_SCORE + 1;
+pub const DRAW_SCORE: Score = 0;
+
+// half-width of the first aspiration window around the previous
+// iteration's score, doubled each time the score falls outside it
+const ASPIRATION_WINDOW: Score = 50;
+// shallower iterations are cheap and their scores swing too much to aim at
+const ASPIRATION_MIN_DEPTH: u8 = 4;
+
+// a capture that leaves the side to move this far below alpha, even after
+// winning the captured piece, isn't searched in quiescence
+const DELTA_MARGIN: Score = 200;
+
+// kept back from the clock for the time it takes to send the move
+const MOVE_OVERHEAD: Duration = Duration::from_millis(50);
+// moves the remaining clock time is spread over when movestogo isn't given
+const DEFAULT_MOVES_TO_GO: u32 = 30;
+
+pub fn is_mate_score(score: Score) -> bool {
+    score.abs() > MAX_EVAL
+}
+
+// score of the side to move when it is mated `ply` half-moves from the root
+fn mated_in(ply: u8) -> Score {
+    -(MATE_SCORE - ply as Score)
+}
+
+// full moves until mate for a white-relative score, positive when white
+// mates, and 0 when the side to move is already mated
+pub fn mate_in(score: Score) -> Option<i32> {
+    if !is_mate_score(score) {
+        return None;
+    }
+    let plies = MATE_SCORE - score.abs();
+    let moves = (plies + 1) / 2;
+    Some(if score > 0 { moves } else { -moves })
+}
+
+// a white-relative score, in pawns
+pub fn score_as_string(score: Score) -> String {
+    match mate_in(score) {
+        Some(0) if score > 0 => "black is checkmated".to_string(),
+        Some(0) => "white is checkmated".to_string(),
+        Some(n) if n > 0 => format!("white mates in {}", n),
+        Some(n) => format!("black mates in {}", -n),
+        None => format!("{:.2}", score as f32 / 100.0),
+    }
+}
+
+/*
+What may end a search, following the UCI `go` command. The search stops at
+the first limit it reaches; with none set it runs until MAX_PLY, so an
+unbounded search should come with a `stop` flag. Limits are only checked
+once the first iteration is done, so there is always a move to play.
+A search limited only by depth and nodes is deterministic: the same
+position, history and limits give the same move and score on every run,
+as long as the table starts out cleared. Time limits and the stop flag
+end the search wherever it happens to be.
+ */
+#[derive(Clone, Debug, Default)]
+pub struct SearchLimits {
+    pub depth: Option<u8>,
+    // time for this move
+    pub movetime: Option<Duration>,
+    pub nodes: Option<u64>,
+    // the clocks and increments, used to budget the time for this move
+    pub wtime: Option<Duration>,
+    pub btime: Option<Duration>,
+    pub winc: Option<Duration>,
+    pub binc: Option<Duration>,
+    // moves until the next time control
+    pub movestogo: Option<u32>,
+    // ignore the other limits and search until `stop` is raised
+    pub infinite: bool,
+    // raised from another thread to end the search early
+    pub stop: Option<Arc<AtomicBool>>,
+}
+
+impl SearchLimits {
+    /*
+    (soft, hard) time limits for this move. no new iteration is started
+    after the soft limit, since the next one usually takes longer than all
+    the previous ones together, and the search is abandoned at the hard one.
+    with only the opponent's clock given, ours is taken to be the same
+     */
+    fn time_budget(&self, side: PieceColor) -> Option<(Duration, Duration)> {
+        if self.infinite {
+            return None;
+        }
+        if let Some(movetime) = self.movetime {
+            return Some((movetime, movetime));
+        }
+        let (own, other) = match side {
+            PieceColor::WHITE => ((self.wtime, self.winc), (self.btime, self.binc)),
+            PieceColor::BLACK => ((self.btime, self.binc), (self.wtime, self.winc)),
+        };
+        let (time, inc) = match own {
+            (Some(time), inc) => (time, inc.unwrap_or_default()),
+            (None, _) => (other.0?, other.1.unwrap_or_default()),
+        };
+        let available = time.saturating_sub(MOVE_OVERHEAD);
+        let moves_to_go = self.movestogo.unwrap_or(DEFAULT_MOVES_TO_GO).max(1);
+        let target = (available / moves_to_go + inc * 3 / 4).min(available);
+        Some((target / 2, (target * 2).min(available)))
+    }
+}
+
+// what a search found, and how much work it took
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchResult {
+    pub best_move: Option<Move>,
+    // centipawns, positive when white is better
+    pub score: Score,
+    // the line both sides are expected to play, starting with best_move.
+    // it stops short of `depth` only where the game ends, in mate or a draw
+    pub pv: Vec<Move>,
+    // depth of the last iteration that completed
+    pub depth: u8,
+    // the furthest ply from the root any node reached, quiescence included
+    pub seldepth: u8,
+    pub nodes: u64,
+    pub elapsed: Duration,
+    // beta cutoffs, and those caused by the first move searched
+    pub cutoffs: u64,
+    pub first_move_cutoffs: u64,
+    // moves searched with a null window, and those that had to be searched
+    // again because they beat the best move
+    pub null_window_searches: u64,
+    pub pvs_researches: u64,
+    // root searches whose score fell outside the aspiration window
+    pub aspiration_fail_highs: u64,
+    pub aspiration_fail_lows: u64,
+}
+
+impl SearchResult {
+    pub fn nps(&self) -> u64 {
+        let micros = self.elapsed.as_micros().max(1);
+        (self.nodes as u128 * 1_000_000 / micros) as u64
+    }
+    // share of the cutoffs found by the first move, 1.0 is perfect ordering
+    pub fn cutoff_rate(&self) -> f32 {
+        if self.cutoffs == 0 {
+            return 0.0;
+        }
+        self.first_move_cutoffs as f32 / self.cutoffs as f32
+    }
+}
+
+// in the style of a UCI info line
+impl fmt::Display for SearchResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "depth {} seldepth {} score {} nodes {} nps {} time {}ms cutoffs {:.1}% \
+             researches {}/{} fail high {} fail low {} pv",
+            self.depth,
+            self.seldepth,
+            score_as_string(self.score),
+            self.nodes,
+            self.nps(),
+            self.elapsed.as_millis(),
+            self.cutoff_rate() * 100.0,
+            self.pvs_researches,
+            self.null_window_searches,
+            self.aspiration_fail_highs,
+            self.aspiration_fail_lows
+        )?;
+        for m in &self.pv {
+            write!(f, " {}", m)?;
+        }
+        Ok(())
+    }
+}
+
+// state shared by every node of one search
+struct SearchState<'a> {
+    tt: &'a mut TranspositionTable,
+    nodes_scanned: u64,
+    seldepth: u8,
+    // triangular PV table: pv[ply] is the best line found from the node
+    // being searched at `ply`
+    pv: Vec<Vec<Move>>,
+    // hashes of the positions before the one being searched, from the
+    // start of the game, used to find repetitions
+    path: Vec<u64>,
+    ordering: MoveOrdering,
+    null_window_searches: u64,
+    pvs_researches: u64,
+    aspiration_fail_highs: u64,
+    aspiration_fail_lows: u64,
+    deadline: Option<Instant>,
+    node_limit: Option<u64>,
+    stop: Option<Arc<AtomicBool>>,
+    // false during the first iteration, which always runs to the end
+    can_stop: bool,
+    // set when a limit is reached, after which every node returns at once
+    stopped: bool,
+}
+
+impl SearchState<'_> {
+    // called on entering a node at `ply`
+    fn visit(&mut self, ply: u8) {
+        self.nodes_scanned += 1;
+        self.seldepth = self.seldepth.max(ply);
+    }
+    // `m` is the new best move at `ply`, followed by the line below it
+    fn update_pv(&mut self, ply: u8, m: Move) {
+        let (line, rest) = self.pv.split_at_mut(ply as usize + 1);
+        let line = &mut line[ply as usize];
+        line.clear();
+        line.push(m);
+        line.extend_from_slice(&rest[0]);
+    }
+    fn should_stop(&mut self) -> bool {
+        if self.stopped || !self.can_stop {
+            return self.stopped;
+        }
+        if self.node_limit.is_some_and(|n| self.nodes_scanned >= n) {
+            self.stopped = true;
+        }
+        // the clock and the flag are only looked at every so often
+        if self.nodes_scanned.is_multiple_of(1024) {
+            self.stopped |= self.deadline.is_some_and(|d| Instant::now() >= d)
+                || self
+                    .stop
+                    .as_ref()
+                    .is_some_and(|s| s.load(Ordering::Relaxed));
+        }
+        self.stopped
+    }
+}
+
+impl Board {
+    /*
+    iterative deepening: searches to depth 1, 2, ... until a limit is
+    reached, and returns the result of the last iteration that completed.
+    each iteration starts with the best moves the previous one left in the
+    table, which more than pays for searching the shallow depths again.
+    the table can be reused across searches of the same game, clear it
+    before starting a new one. `history` holds the hashes of the earlier
+    positions of the game, as for status, so that repetitions are scored
+    as draws. the score is in centipawns, positive when white is better
+     */
+    pub fn search(
+        &mut self,
+        limits: &SearchLimits,
+        tt: &mut TranspositionTable,
+        history: &[u64],
+    ) -> SearchResult {
+        let now = Instant::now();
+        let budget = limits.time_budget(self.side_to_move);
+        let max_depth = match limits.depth {
+            // the first iteration always completes, so there is a move
+            Some(depth) if !limits.infinite => depth.max(1),
+            _ => MAX_PLY,
+        };
+        let mut state = SearchState {
+            tt,
+            nodes_scanned: 0,
+            seldepth: 0,
+            pv: vec![Vec::new(); MAX_PLY as usize + 2],
+            path: history.to_vec(),
+            ordering: MoveOrdering::new(),
+            null_window_searches: 0,
+            pvs_researches: 0,
+            aspiration_fail_highs: 0,
+            aspiration_fail_lows: 0,
+            deadline: budget.map(|(_, hard)| now + hard),
+            node_limit: limits.nodes.filter(|_| !limits.infinite),
+            stop: limits.stop.clone(),
+            can_stop: false,
+            stopped: false,
+        };
+        let mut result = SearchResult {
+            best_move: None,
+            score: DRAW_SCORE,
+            pv: Vec::new(),
+            depth: 0,
+            seldepth: 0,
+            nodes: 0,
+            elapsed: Duration::ZERO,
+            cutoffs: 0,
+            first_move_cutoffs: 0,
+            null_window_searches: 0,
+            pvs_researches: 0,
+            aspiration_fail_highs: 0,
+            aspiration_fail_lows: 0,
+        };
+        for depth in 1..=max_depth {
+            let guess = result.score * self.side_to_move.get_value();
+            let guess = Some(guess).filter(|_| depth >= ASPIRATION_MIN_DEPTH);
+            let (score, mov) = self.search_root(depth, guess, &mut state);
+            if state.stopped {
+                break;
+            }
+            let score = score * self.side_to_move.get_value();
+            result.best_move = mov;
+            result.score = score;
+            result.pv = state.pv[0].clone();
+            result.depth = depth;
+            state.can_stop = true;
+            // no legal moves, or a mate that was found at full width, so
+            // searching deeper can't change it
+            if mov.is_none() || (is_mate_score(score) && !limits.infinite) {
+                break;
+            }
+            if budget.is_some_and(|(soft, _)| now.elapsed() >= soft) {
+                break;
+            }
+        }
+        result.seldepth = state.seldepth;
+        result.nodes = state.nodes_scanned;
+        result.elapsed = now.elapsed();
+        result.cutoffs = state.ordering.cutoffs;
+        result.first_move_cutoffs = state.ordering.first_move_cutoffs;
+        result.null_window_searches = state.null_window_searches;
+        result.pvs_researches = state.pvs_researches;
+        result.aspiration_fail_highs = state.aspiration_fail_highs;
+        result.aspiration_fail_lows = state.aspiration_fail_lows;
+        result
+    }
+    /*
+    one iteration, searched first with an aspiration window around `guess`,
+    the score of the previous one. most of the time the score lands inside
+    it and the narrow window prunes more; when it doesn't, the side it fell
+    out of is widened and the root searched again, until the score is exact
+     */
+    fn search_root(
+        &mut self,
+        depth: u8,
+        guess: Option<Score>,
+        state: &mut SearchState,
+    ) -> (Score, Option<Move>) {
+        let guess = match guess.filter(|g| !is_mate_score(*g)) {
+            Some(guess) => guess,
+            None => return self.negamax(depth, 0, -INFINITY, INFINITY, None, state),
+        };
+        let mut delta = ASPIRATION_WINDOW;
+        let (mut alpha, mut beta) = (guess - delta, guess + delta);
+        loop {
+            let (score, mov) = self.negamax(depth, 0, alpha, beta, None, state);
+            if state.stopped {
+                return (score, mov);
+            }
+            delta *= 2;
+            if score <= alpha && alpha > -INFINITY {
+                state.aspiration_fail_lows += 1;
+                alpha = (score - delta).max(-INFINITY);
+            } else if score >= beta && beta < INFINITY {
+                state.aspiration_fail_highs += 1;
+                beta = (score + delta).min(INFINITY);
+            } else {
+                return (score, mov);
+            }
+        }
+    }
+    /*
+    negamax: scores are from the point of view of the side to move, and the
+    score of a move is minus the score of the position it leads to. `ply` is
+    the distance from the root, used to score mates, and `prev` the move
+    that led here. the result is meaningless once `state.stopped` is set
+     */
+    fn negamax(
+        &mut self,
+        depth: u8,
+        ply: u8,
+        mut alpha: Score,
+        mut beta: Score,
+        prev: Option<Move>,
+        state: &mut SearchState,
+    ) -> (Score, Option<Move>) {
+        state.visit(ply);
+        state.pv[ply as usize].clear();
+        if state.should_stop() {
+            return (DRAW_SCORE, None);
+        }
+        // a position that repeats is a draw, since whichever side chose to
+        // repeat it can do so again. this is checked before the table,
+        // whose entries don't know how the position was reached
+        if ply > 0 && (self.repetition_count(&state.path) > 1 || self.has_insufficient_material()) {
+            return (DRAW_SCORE, None);
+        }
+        let (alpha_orig, beta_orig) = (alpha, beta);
+        let entry = state.tt.probe(self.hash);
+        // the table only cuts off null window searches. the root and the
+        // other nodes on the principal variation are always searched, so
+        // that the whole line is there to report
+        let pv_node = beta - alpha > 1;
+        if let Some(e) = entry.filter(|_| ply > 0 && !pv_node) {
+            if e.depth >= depth {
+                let score = score_from_tt(e.score, ply);
+                match e.bound {
+                    Bound::EXACT => return (score, e.best_move),
+                    Bound::LOWER => alpha = alpha.max(score),
+                    Bound::UPPER => beta = beta.min(score),
+                }
+                if alpha >= beta {
+                    return (score, e.best_move);
+                }
+            }
+        }
+        let mut v_moves = all_possible_valid_moves(self);
+        if v_moves.is_empty() {
+            // mated, or stalemate which is a draw
+            let score = if self.has_check(&self.side_to_move) {
+                mated_in(ply)
+            } else {
+                DRAW_SCORE
+            };
+            state.tt.store(
+                self.hash,
+                depth,
+                score_to_tt(score, ply),
+                Bound::EXACT,
+                None,
+            );
+            return (score, None);
+        }
+        // checkmate on the hundredth half-move still counts, so this comes
+        // after the test for no moves
+        if ply > 0 && self.halfmove_clock >= 100 {
+            return (DRAW_SCORE, None);
+        }
+        if depth == 0 || ply == MAX_PLY {
+            let score = self.quiescence(ply, alpha, beta, state);
+            if !state.stopped {
+                let bound = bound(score, alpha_orig, beta_orig);
+                state
+                    .tt
+                    .store(self.hash, 0, score_to_tt(score, ply), bound, None);
+            }
+            return (score, None);
+        }
+        let hash_move = entry.and_then(|e| e.best_move);
+        state
+            .ordering
+            .order(self, &mut v_moves, hash_move, ply, prev);
+        let mut best_score = -INFINITY;
+        let mut best_move = None;
+        for (i, m) in v_moves.into_iter().enumerate() {
+            state.path.push(self.hash);
+            let ctx = self.make_move(m);
+            // principal variation search: once a move has been searched with
+            // the full window, the others are only checked to be worse with a
+            // null window, and searched again if they turn out better
+            let mut score = if i == 0 {
+                -self
+                    .negamax(depth - 1, ply + 1, -beta, -alpha, Some(m), state)
+                    .0
+            } else {
+                state.null_window_searches += 1;
+                -self
+                    .negamax(depth - 1, ply + 1, -alpha - 1, -alpha, Some(m), state)
+                    .0
+            };
+            if i > 0 && score > alpha && score < beta && !state.stopped {
+                state.pvs_researches += 1;
+                score = -self
+                    .negamax(depth - 1, ply + 1, -beta, -alpha, Some(m), state)
+                    .0;
+            }
+            self.unmake_move(ctx);
+            state.path.pop();
+            if state.stopped {
+                return (DRAW_SCORE, None);
+            }
+            if score > best_score {
+                best_score = score;
+                best_move = Some(m);
+            }
+            if score > alpha {
+                alpha = score;
+                state.update_pv(ply, m);
+            }
+            if alpha >= beta {
+                state.ordering.record_cutoff(self, m, i, depth, ply, prev);
+                break;
+            }
+        }
+        state.tt.store(
+            self.hash,
+            depth,
+            score_to_tt(best_score, ply),
+            bound(best_score, alpha_orig, beta_orig),
+            best_move,
+        );
+
+        (best_score, best_move)
+    }
+    /*
+    searches captures and promotions until the position is quiet, so that
+    the static evaluation is never taken in the middle of an exchange. the
+    side to move may stand pat on the evaluation instead of capturing,
+    except in check, where every evasion is searched and mate is scored
+    */
+    fn quiescence(
+        &mut self,
+        ply: u8,
+        mut alpha: Score,
+        beta: Score,
+        state: &mut SearchState,
+    ) -> Score {
+        state.visit(ply);
+        if state.should_stop() {
+            return DRAW_SCORE;
+        }
+        let in_check = self.has_check(&self.side_to_move);
+        let mut v_moves = all_possible_valid_moves(self);
+        if v_moves.is_empty() {
+            return if in_check { mated_in(ply) } else { DRAW_SCORE };
+        }
+        if ply == MAX_PLY {
+            return self.evaluate();
+        }
+        let mut best_score = if in_check {
+            -INFINITY
+        } else {
+            let stand_pat = self.evaluate();
+            if stand_pat >= beta {
+                return stand_pat;
+            }
+            v_moves.retain(|m| {
+                if m.promotion_piece().is_some() {
+                    return true;
+                }
+                // delta pruning
+                m.is_capture() && stand_pat + self.captured_value(*m) + DELTA_MARGIN > alpha
+            });
+            stand_pat
+        };
+        alpha = alpha.max(best_score);
+        v_moves.sort_by_cached_key(|m| -mvv_lva(self, *m));
+        for m in v_moves {
+            let ctx = self.make_move(m);
+            let score = -self.quiescence(ply + 1, -beta, -alpha, state);
+            self.unmake_move(ctx);
+            if state.stopped {
+                return DRAW_SCORE;
+            }
+            best_score = best_score.max(score);
+            alpha = alpha.max(score);
+            if alpha >= beta {
+                break;
+            }
+        }
+        best_score
+    }
+    // centipawns won by the capture `m`, 0 for a move that isn't a capture
+    fn captured_value(&self, m: Move) -> Score {
+        let captured = if m.flags() == EN_PASSANT_CAPTURE {
+            Some(Piece {
+                color: self.side_to_move.opponent_color(),
+                piece_type: PieceType::PAWN,
+            })
+        } else {
+            self.get_piece(m.to())
+        };
+        captured.map_or(0, |p| (get_piece_weight(&p) * 100.0) as Score)
+    }
+}
+
+fn bound(score: Score, alpha: Score, beta: Score) -> Bound {
+    if score <= alpha {
+        Bound::UPPER
+    } else if score >= beta {
+        Bound::LOWER
+    } else {
+        Bound::EXACT
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::{
+        board::{create_board, START_FEN},
+        moves::match_valid_move,
+        status::GameStatus,
+    };
+
+    fn depth_limit(depth: u8) -> SearchLimits {
+        SearchLimits {
+            depth: Some(depth),
+            ..Default::default()
+        }
+    }
+
+    fn search(fen: &str, depth: u8) -> SearchResult {
+        let mut board = create_board(fen).unwrap();
+        board.search(&depth_limit(depth), &mut TranspositionTable::default(), &[])
+    }
+
+    fn legal(fen: &str, uci: &str) -> Move {
+        let mut board = create_board(fen).unwrap();
+        match_valid_move(&mut board, &uci.parse().unwrap()).unwrap()
+    }
+
+    // the queen would be lost to the recapture, which a search that stops
+    // at a fixed depth doesn't see
+    #[test]
+    fn queen_does_not_take_a_defended_pawn() {
+        let fen = "4k3/8/2p5/3p4/8/8/3Q4/4K3 w - - 0 1";
+        for depth in [1, 3] {
+            let result = search(fen, depth);
+            assert_ne!(result.best_move, Some(legal(fen, "d2d5")));
+            // still a queen against two pawns
+            assert!(result.score > 500, "{}", result);
+        }
+        let fen = "4k3/3q4/8/8/3P4/2P5/8/4K3 b - - 0 1";
+        let result = search(fen, 1);
+        assert_ne!(result.best_move, Some(legal(fen, "d7d4")));
+        assert!(result.score < -500, "{}", result);
+    }
+
+    #[test]
+    fn rook_does_not_take_a_defended_pawn() {
+        let fen = "4k3/8/4p3/3p4/8/8/8/3RK3 w - - 0 1";
+        let result = search(fen, 1);
+        assert_ne!(result.best_move, Some(legal(fen, "d1d5")));
+        assert!(result.score > 200, "{}", result);
+    }
+
+    #[test]
+    fn attacked_queen_moves_away_instead_of_taking() {
+        let fen = "4k3/8/8/p7/1p6/2Q5/8/4K3 w - - 0 1";
+        let result = search(fen, 1);
+        assert_ne!(result.best_move, Some(legal(fen, "c3b4")));
+        assert!(result.score > 500, "{}", result);
+    }
+
+    // captures that win material even after the recapture
+    #[test]
+    fn takes_material_that_is_worth_the_recapture() {
+        // knight for rook, only a pawn down afterwards
+        let fen = "4k3/8/4p3/3r4/8/4N3/8/4K3 w - - 0 1";
+        let result = search(fen, 1);
+        assert_eq!(result.best_move, Some(legal(fen, "e3d5")));
+        assert!(result.score > -100, "{}", result);
+
+        let fen = "4k3/8/8/3q4/8/8/8/3RK3 w - - 0 1";
+        let result = search(fen, 2);
+        assert_eq!(result.best_move, Some(legal(fen, "d1d5")));
+        assert!(result.score > 400, "{}", result);
+    }
+
+    #[test]
+    fn promotes_a_pawn_that_cannot_be_stopped() {
+        let fen = "8/3P4/8/8/8/k7/8/4K3 w - - 0 1";
+        let result = search(fen, 1);
+        assert_eq!(result.best_move, Some(legal(fen, "d7d8q")));
+        assert!(result.score > 500, "{}", result);
+    }
+
+    #[test]
+    fn reports_mate_from_the_root() {
+        let result = search("R5k1/5ppp/8/8/8/8/8/6K1 b - - 1 1", 1);
+        assert_eq!(result.best_move, None);
+        assert_eq!(score_as_string(result.score), "black is checkmated");
+        let result = search("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", 2);
+        assert_eq!(
+            result.best_move,
+            Some(legal("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", "a1a8"))
+        );
+        assert_eq!(score_as_string(result.score), "white mates in 1");
+    }
+
+    #[test]
+    fn mate_in_two_line_ends_in_mate() {
+        let fen = "7k/8/8/8/8/8/8/KRR5 w - - 0 1";
+        let result = search(fen, 4);
+        assert_eq!(score_as_string(result.score), "white mates in 2");
+        assert_eq!(result.pv.len(), 3, "{}", result);
+        let mut board = create_board(fen).unwrap();
+        for m in result.pv.iter() {
+            board.make_move(*m);
+        }
+        assert_eq!(
+            board.status(&[]),
+            GameStatus::CHECKMATE {
+                winner: PieceColor::WHITE
+            }
+        );
+    }
+
+    #[test]
+    fn depth_zero_searches_one_ply() {
+        let result = search(START_FEN, 0);
+        assert_eq!(result.depth, 1);
+        assert!(result.best_move.is_some());
+    }
+
+    #[test]
+    fn budgets_from_the_clock_that_is_given() {
+        let limits = SearchLimits {
+            wtime: Some(Duration::from_millis(1000)),
+            ..Default::default()
+        };
+        let budget = limits.time_budget(PieceColor::WHITE);
+        assert!(budget.is_some());
+        assert_eq!(limits.time_budget(PieceColor::BLACK), budget);
+        assert_eq!(SearchLimits::default().time_budget(PieceColor::BLACK), None);
+    }
+
+    // a search by depth or nodes gives the same result every time, whether
+    // the table is new or was used before and cleared
+    #[test]
+    fn searches_are_reproducible() {
+        let fens = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r1bqkbnr/pppp1ppp/2n5/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R b KQkq - 3 3",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        ];
+        let limits = [
+            depth_limit(2),
+            SearchLimits {
+                nodes: Some(500),
+                ..Default::default()
+            },
+        ];
+        let mut used_tt = TranspositionTable::default();
+        for fen in fens {
+            for limits in &limits {
+                let mut board = create_board(fen).unwrap();
+                let first = board.search(limits, &mut TranspositionTable::default(), &[]);
+                used_tt.clear();
+                let second = board.search(limits, &mut used_tt, &[]);
+                assert_eq!(first.best_move, second.best_move, "{}", fen);
+                assert_eq!(first.score, second.score, "{}", fen);
+                assert_eq!(first.pv, second.pv, "{}", fen);
+                assert_eq!(first.nodes, second.nodes, "{}", fen);
+                // and the board is left as it was
+                assert_eq!(board.to_fen(), fen);
+                assert_eq!(board.hash, board.compute_hash());
+            }
+        }
+    }
+}
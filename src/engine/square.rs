@@ -0,0 +1,84 @@
+use std::{fmt, str::FromStr};
+
+use super::bitboard::Bitboard;
+
+/*
+A square on the board. Files and ranks are counted the way they are
+written: file 0 is the a-file and rank 0 is the first rank, so e1 is
+(4, 0). Internally squares are numbered from a8 (0) to h1 (63), which is
+also the bit order of a Bitboard.
+ */
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Square(u8);
+
+impl Square {
+    pub const A1: Square = Square(56);
+    pub const E1: Square = Square(60);
+    pub const H1: Square = Square(63);
+    pub const A8: Square = Square(0);
+    pub const E8: Square = Square(4);
+    pub const H8: Square = Square(7);
+
+    // `index` must be below 64
+    pub fn from_index(index: u8) -> Square {
+        debug_assert!(index < 64, "square index out of range: {}", index);
+        Square(index)
+    }
+    pub fn from_coords(file: u8, rank: u8) -> Option<Square> {
+        if file >= 8 || rank >= 8 {
+            return None;
+        }
+        Some(Square((7 - rank) * 8 + file))
+    }
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+    pub fn file(self) -> u8 {
+        self.0 % 8
+    }
+    pub fn rank(self) -> u8 {
+        7 - self.0 / 8
+    }
+    pub fn bit(self) -> Bitboard {
+        1 << self.0
+    }
+    // the square `files` to the right and `ranks` up, if it is on the board
+    pub fn offset(self, files: i8, ranks: i8) -> Option<Square> {
+        let file = self.file() as i8 + files;
+        let rank = self.rank() as i8 + ranks;
+        if !(0..8).contains(&file) || !(0..8).contains(&rank) {
+            return None;
+        }
+        Square::from_coords(file as u8, rank as u8)
+    }
+    // all 64 squares, a8 first
+    pub fn all() -> impl Iterator<Item = Square> {
+        (0..64).map(Square)
+    }
+}
+
+impl fmt::Display for Square {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}{}",
+            (b'a' + self.file()) as char,
+            (b'1' + self.rank()) as char
+        )
+    }
+}
+
+impl FromStr for Square {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Square, String> {
+        let bytes = s.as_bytes();
+        if bytes.len() != 2 {
+            return Err("invalid position".to_string());
+        }
+        let (f, r) = (bytes[0], bytes[1]);
+        if !(b'a'..=b'h').contains(&f) || !(b'1'..=b'8').contains(&r) {
+            return Err("invalid position".to_string());
+        }
+        Ok(Square::from_coords(f - b'a', r - b'1').unwrap())
+    }
+}
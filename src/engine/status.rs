@@ -0,0 +1,214 @@
+use std::fmt;
+
+use super::{
+    bitboard::{Bitboard, EMPTY},
+    board::Board,
+    moves::all_possible_valid_moves,
+    piece::{PieceColor, PieceType},
+};
+
+// a8 is a light square, and bit 0 is a8
+const LIGHT_SQUARES: Bitboard = 0xaa55_aa55_aa55_aa55;
+
+/*
+Whether the game is over in a position, and why. The fifty-move rule and
+threefold repetition only give a player the right to claim a draw, the
+other results end the game on their own.
+ */
+#[allow(non_camel_case_types, clippy::upper_case_acronyms)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum GameStatus {
+    ONGOING,
+    // the side to move is mated
+    CHECKMATE { winner: PieceColor },
+    STALEMATE,
+    INSUFFICIENT_MATERIAL,
+    FIVEFOLD_REPETITION,
+    SEVENTY_FIVE_MOVE_RULE,
+    THREEFOLD_REPETITION,
+    FIFTY_MOVE_RULE,
+}
+
+impl GameStatus {
+    // true for draws a player has to claim, the game goes on otherwise
+    pub fn is_claimable(&self) -> bool {
+        matches!(
+            self,
+            GameStatus::THREEFOLD_REPETITION | GameStatus::FIFTY_MOVE_RULE
+        )
+    }
+    pub fn is_game_over(&self) -> bool {
+        *self != GameStatus::ONGOING && !self.is_claimable()
+    }
+}
+
+impl fmt::Display for GameStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GameStatus::ONGOING => write!(f, "ongoing"),
+            GameStatus::CHECKMATE { winner } => match winner {
+                PieceColor::WHITE => write!(f, "checkmate, white wins"),
+                PieceColor::BLACK => write!(f, "checkmate, black wins"),
+            },
+            GameStatus::STALEMATE => write!(f, "draw by stalemate"),
+            GameStatus::INSUFFICIENT_MATERIAL => write!(f, "draw by insufficient material"),
+            GameStatus::FIVEFOLD_REPETITION => write!(f, "draw by fivefold repetition"),
+            GameStatus::SEVENTY_FIVE_MOVE_RULE => write!(f, "draw by the seventy-five-move rule"),
+            GameStatus::THREEFOLD_REPETITION => write!(f, "draw by threefold repetition"),
+            GameStatus::FIFTY_MOVE_RULE => write!(f, "draw by the fifty-move rule"),
+        }
+    }
+}
+
+impl Board {
+    /*
+    `history` holds the hashes of the positions before this one, oldest
+    first, and is only used to count repetitions; pass an empty slice when
+    the earlier moves are unknown. checkmate and stalemate take precedence
+    over every draw rule, and the draws that end the game over the ones
+    that have to be claimed
+     */
+    pub fn status(&mut self, history: &[u64]) -> GameStatus {
+        if all_possible_valid_moves(self).is_empty() {
+            let side = self.side_to_move;
+            return if self.has_check(&side) {
+                GameStatus::CHECKMATE {
+                    winner: side.opponent_color(),
+                }
+            } else {
+                GameStatus::STALEMATE
+            };
+        }
+        if self.has_insufficient_material() {
+            return GameStatus::INSUFFICIENT_MATERIAL;
+        }
+        let repetitions = self.repetition_count(history);
+        if repetitions >= 5 {
+            return GameStatus::FIVEFOLD_REPETITION;
+        }
+        if self.halfmove_clock >= 150 {
+            return GameStatus::SEVENTY_FIVE_MOVE_RULE;
+        }
+        if repetitions >= 3 {
+            return GameStatus::THREEFOLD_REPETITION;
+        }
+        if self.halfmove_clock >= 100 {
+            return GameStatus::FIFTY_MOVE_RULE;
+        }
+        GameStatus::ONGOING
+    }
+    /*
+    how many times this position has occurred, counting this occurrence.
+    only the last `halfmove_clock` entries of `history` are looked at,
+    since a capture or pawn move makes every earlier position unreachable.
+    the hash only includes the en passant square when the capture is
+    possible, as the rules require
+     */
+    pub fn repetition_count(&self, history: &[u64]) -> usize {
+        let window = history.len().min(self.halfmove_clock as usize);
+        1 + history[history.len() - window..]
+            .iter()
+            .filter(|h| **h == self.hash)
+            .count()
+    }
+    // neither side can mate with any series of legal moves: bare kings,
+    // a single minor piece, or only bishops that all stand on one color
+    pub fn has_insufficient_material(&self) -> bool {
+        let pawns_and_majors = self.pieces[PieceType::PAWN as usize]
+            | self.pieces[PieceType::ROOK as usize]
+            | self.pieces[PieceType::QUEEN as usize];
+        if pawns_and_majors != EMPTY {
+            return false;
+        }
+        let knights = self.pieces[PieceType::KNIGHT as usize];
+        let bishops = self.pieces[PieceType::BISHOP as usize];
+        if (knights | bishops).count_ones() <= 1 {
+            return true;
+        }
+        knights == EMPTY && (bishops & LIGHT_SQUARES == EMPTY || bishops & !LIGHT_SQUARES == EMPTY)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::{
+        board::{create_board, START_FEN},
+        game::Game,
+    };
+
+    fn status(fen: &str) -> GameStatus {
+        create_board(fen).unwrap().status(&[])
+    }
+
+    #[test]
+    fn mate_and_stalemate() {
+        let mated = status("R5k1/5ppp/8/8/8/8/8/6K1 b - - 1 1");
+        assert_eq!(
+            mated,
+            GameStatus::CHECKMATE {
+                winner: PieceColor::WHITE
+            }
+        );
+        assert!(mated.is_game_over());
+        let stalemate = status("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1");
+        assert_eq!(stalemate, GameStatus::STALEMATE);
+        assert!(stalemate.is_game_over());
+    }
+
+    #[test]
+    fn insufficient_material() {
+        for fen in [
+            "4k3/8/8/8/8/8/8/4K3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/4KN2 w - - 0 1",
+            // bishops on the same color
+            "4kb2/8/8/8/8/8/8/2B1K3 w - - 0 1",
+        ] {
+            assert_eq!(status(fen), GameStatus::INSUFFICIENT_MATERIAL, "{}", fen);
+        }
+        assert_eq!(
+            status("4kb2/8/8/8/8/8/8/3BK3 w - - 0 1"),
+            GameStatus::ONGOING
+        );
+    }
+
+    #[test]
+    fn move_rules_and_repetition() {
+        let fifty = status("4k3/8/8/8/8/8/8/R3K3 w - - 100 80");
+        assert_eq!(fifty, GameStatus::FIFTY_MOVE_RULE);
+        assert!(fifty.is_claimable() && !fifty.is_game_over());
+        let seventy_five = status("4k3/8/8/8/8/8/8/R3K3 w - - 150 100");
+        assert_eq!(seventy_five, GameStatus::SEVENTY_FIVE_MOVE_RULE);
+        assert!(!seventy_five.is_claimable() && seventy_five.is_game_over());
+
+        let mut board = create_board("4k3/8/8/8/8/8/8/R3K3 w - - 8 10").unwrap();
+        let hash = board.hash;
+        assert_eq!(board.status(&[hash, 1, 2, 3]), GameStatus::ONGOING);
+        let threefold = board.status(&[hash, 1, 2, 3, hash, 4, 5, 6]);
+        assert_eq!(threefold, GameStatus::THREEFOLD_REPETITION);
+        assert!(threefold.is_claimable());
+    }
+
+    // the en passant square after e2e4 can't be used, so the position
+    // after it is the same as the one the knights return to
+    #[test]
+    fn repetition_after_a_double_push() {
+        let moves = [
+            "e2e4", "g8f6", "g1f3", "f6g8", "f3g1", "g8f6", "g1f3", "f6g8", "f3g1",
+        ];
+        let moves: Vec<_> = moves.iter().map(|m| m.parse().unwrap()).collect();
+        let mut game = Game::replay(START_FEN, &moves).unwrap();
+        assert_eq!(game.repetition_count(), 3);
+        assert_eq!(game.status(), GameStatus::THREEFOLD_REPETITION);
+
+        let hash = |fen: &str| create_board(fen).unwrap().hash;
+        assert_eq!(
+            hash("4k3/8/8/8/4P3/8/8/4K3 b - e3 0 1"),
+            hash("4k3/8/8/8/4P3/8/8/4K3 b - - 0 1")
+        );
+        assert_ne!(
+            hash("4k3/8/8/8/3pP3/8/8/4K3 b - e3 0 1"),
+            hash("4k3/8/8/8/3pP3/8/8/4K3 b - - 0 1")
+        );
+    }
+}
@@ -0,0 +1,109 @@
+use super::{
+    search::{is_mate_score, Score},
+    Move,
+};
+
+pub const DEFAULT_TT_SIZE_MB: usize = 16;
+
+// how the stored score relates to the true score of the position
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Bound {
+    EXACT,
+    // the search failed high, the true score is at least this
+    LOWER,
+    // the search failed low, the true score is at most this
+    UPPER,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct TTEntry {
+    pub key: u64,
+    pub depth: u8,
+    // mate scores count plies from this position, see score_to_tt
+    pub score: Score,
+    pub bound: Bound,
+    pub best_move: Option<Move>,
+}
+
+// fixed size hash table of search results, indexed by the low bits of the
+// zobrist hash. a slot holds one entry and collisions overwrite
+pub struct TranspositionTable {
+    entries: Vec<Option<TTEntry>>,
+}
+
+impl TranspositionTable {
+    pub fn new(size_mb: usize) -> TranspositionTable {
+        let mut tt = TranspositionTable {
+            entries: Vec::new(),
+        };
+        tt.resize(size_mb);
+        tt
+    }
+    // also clears the table. the number of slots is rounded down to a
+    // power of two so the index is a mask of the hash
+    pub fn resize(&mut self, size_mb: usize) {
+        let slots = (size_mb * 1024 * 1024 / std::mem::size_of::<Option<TTEntry>>()).max(1);
+        let slots = 1 << (usize::BITS - 1 - slots.leading_zeros());
+        if slots == self.entries.len() {
+            self.clear();
+        } else {
+            self.entries = vec![None; slots];
+        }
+    }
+    pub fn clear(&mut self) {
+        self.entries.fill(None);
+    }
+    fn index(&self, key: u64) -> usize {
+        (key as usize) & (self.entries.len() - 1)
+    }
+    pub fn probe(&self, key: u64) -> Option<TTEntry> {
+        self.entries[self.index(key)].filter(|e| e.key == key)
+    }
+    // keeps the deeper result when the same position is stored twice,
+    // otherwise the newest entry wins
+    pub fn store(
+        &mut self,
+        key: u64,
+        depth: u8,
+        score: Score,
+        bound: Bound,
+        best_move: Option<Move>,
+    ) {
+        let i = self.index(key);
+        if let Some(e) = self.entries[i] {
+            if e.key == key && e.depth > depth {
+                return;
+            }
+        }
+        self.entries[i] = Some(TTEntry {
+            key,
+            depth,
+            score,
+            bound,
+            best_move,
+        });
+    }
+}
+
+impl Default for TranspositionTable {
+    fn default() -> Self {
+        TranspositionTable::new(DEFAULT_TT_SIZE_MB)
+    }
+}
+
+// mate scores count plies from the root, but an entry can be found again at
+// any ply, so they are stored as the distance from the entry's position
+pub fn score_to_tt(score: Score, ply: u8) -> Score {
+    if !is_mate_score(score) {
+        return score;
+    }
+    score + score.signum() * ply as Score
+}
+
+pub fn score_from_tt(score: Score, ply: u8) -> Score {
+    if !is_mate_score(score) {
+        return score;
+    }
+    score - score.signum() * ply as Score
+}
@@ -0,0 +1,64 @@
+use std::sync::OnceLock;
+
+use super::{piece::Piece, prng::Prng, square::Square};
+
+/*
+Zobrist keys: one random number per (color, piece type, square), one for
+black to move, one per castling rights combination and one per en passant
+file. A position's hash is the xor of the keys for everything in it, so a
+move only has to xor out what changed and xor in the new state.
+ */
+
+const ZOBRIST_SEED: u64 = 0x2d35_8dcc_aa6c_78a5;
+
+struct ZobristKeys {
+    // indexed by color, piece type and square
+    pieces: [[[u64; 64]; 6]; 2],
+    black_to_move: u64,
+    castling: [u64; 16],
+    en_passant_file: [u64; 8],
+}
+
+static KEYS: OnceLock<ZobristKeys> = OnceLock::new();
+
+fn keys() -> &'static ZobristKeys {
+    KEYS.get_or_init(|| {
+        let mut rng = Prng::new(ZOBRIST_SEED);
+        let mut keys = ZobristKeys {
+            pieces: [[[0; 64]; 6]; 2],
+            black_to_move: rng.next_u64(),
+            castling: [0; 16],
+            en_passant_file: [0; 8],
+        };
+        for key in keys.pieces.iter_mut().flatten().flatten() {
+            *key = rng.next_u64();
+        }
+        // no castling rights hashes to nothing, like an empty square
+        for key in keys.castling.iter_mut().skip(1) {
+            *key = rng.next_u64();
+        }
+        for key in keys.en_passant_file.iter_mut() {
+            *key = rng.next_u64();
+        }
+        keys
+    })
+}
+
+pub fn piece_key(piece: &Piece, sq: Square) -> u64 {
+    keys().pieces[piece.color as usize][piece.piece_type as usize][sq.index()]
+}
+
+pub fn side_key() -> u64 {
+    keys().black_to_move
+}
+
+pub fn castling_key(castling_rights: u8) -> u64 {
+    keys().castling[(castling_rights & 0b1111) as usize]
+}
+
+pub fn en_passant_key(en_passant_square: Option<Square>) -> u64 {
+    match en_passant_square {
+        Some(sq) => keys().en_passant_file[sq.file() as usize],
+        None => 0,
+    }
+}
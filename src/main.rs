@@ -4,9 +4,16 @@ use repress::{
 };
 use stockfish_adapter::StockfishAdapter;
 mod api_utils;
+mod cli;
+mod engine;
 mod stockfish_adapter;
 
 pub fn main() {
+    engine::attacks::init();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        std::process::exit(cli::run(&args));
+    }
     let mut app = repress_app();
     app.middleware(Box::new(
         move |req: &Request, res: &mut Response, mut next: Box<NextFn>| {
@@ -41,32 +48,27 @@ pub fn main() {
             }
             println!("fen: {}", fen);
             stockfish.set_fen(fen.as_str());
-            match body.get("level") {
-                Some(level) => match level.parse::<u32>() {
-                    Ok(level) => stockfish.set_level(level),
-                    Err(_) => {}
-                },
-                None => {}
+            if let Some(level) = body.get("level") {
+                if let Ok(level) = level.parse::<u32>() {
+                    stockfish.set_level(level);
+                }
             }
             let op = body.get("operation");
-            match op {
-                Some(op) => {
-                    if op.eq("bestmove") {
-                        let bestmove = stockfish.bestmove();
-                        res.set_status(200)
-                            .text(format!("{{\"bestmove\": \"{}\"}}", bestmove));
-                    } else if op.eq("legalmoves") {
-                        let legal_moves = stockfish.legal_moves();
-                        res.set_status(200).text(json_list(legal_moves));
-                    } else {
-                        res.set_status(400)
-                            .text("{\"error\": \"invalid operation\"}".to_string());
-                    }
-                    res.end();
-                    stockfish.kill();
-                    return;
+            if let Some(op) = op {
+                if op.eq("bestmove") {
+                    let bestmove = stockfish.bestmove();
+                    res.set_status(200)
+                        .text(format!("{{\"bestmove\": \"{}\"}}", bestmove));
+                } else if op.eq("legalmoves") {
+                    let legal_moves = stockfish.legal_moves();
+                    res.set_status(200).text(json_list(legal_moves));
+                } else {
+                    res.set_status(400)
+                        .text("{\"error\": \"invalid operation\"}".to_string());
                 }
-                None => {}
+                res.end();
+                stockfish.kill();
+                return;
             }
             res.end();
             stockfish.kill();
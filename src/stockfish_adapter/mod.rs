@@ -118,7 +118,7 @@ impl StockfishAdapter {
             match line {
                 Ok(line) => {
                     // println!("{}", line);
-                    if line.len() == 0 {
+                    if line.is_empty() {
                         break;
                     }
                     let colon_index = line.find(":");